@@ -0,0 +1,188 @@
+//! `LD_PRELOAD`-able malloc/free interposer.
+//!
+//! Built as a `cdylib` and injected into the profiled program via `LD_PRELOAD`, this library
+//! resolves the real `malloc`/`calloc`/`realloc`/`free` symbols with `dlsym(RTLD_NEXT, ...)`,
+//! records every allocation/free as a fixed-size [`AllocEvent`], and streams the events over a
+//! Unix domain socket back to the toolkit, which applies them to a `MemoryTracker`. This gives the toolkit real
+//! `active_allocations`/`allocation_count`/`free_count` instead of numbers derived from RSS.
+use libc::{c_void, size_t};
+use std::cell::Cell;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Wire format written to the event socket: fixed-size, no padding ambiguity, one event per
+/// allocation or free. `kind` is `0` for alloc, `1` for free.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AllocEvent {
+    pub kind: u8,
+    pub _pad: [u8; 7],
+    pub address: u64,
+    pub size: u64,
+    pub timestamp_nanos: i64,
+    pub thread_id: u64,
+}
+
+impl AllocEvent {
+    pub const WIRE_SIZE: usize = std::mem::size_of::<AllocEvent>();
+
+    fn alloc(address: u64, size: u64) -> Self {
+        Self {
+            kind: 0,
+            _pad: [0; 7],
+            address,
+            size,
+            timestamp_nanos: now_nanos(),
+            thread_id: thread_id(),
+        }
+    }
+
+    fn free(address: u64) -> Self {
+        Self {
+            kind: 1,
+            _pad: [0; 7],
+            address,
+            size: 0,
+            timestamp_nanos: now_nanos(),
+            thread_id: thread_id(),
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; Self::WIRE_SIZE] {
+        unsafe { std::mem::transmute(self) }
+    }
+}
+
+fn now_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+fn thread_id() -> u64 {
+    unsafe { libc::pthread_self() as u64 }
+}
+
+thread_local! {
+    // Reentrancy guard: the socket connect/write path itself allocates (buffers, the
+    // `UnixStream`'s internal fd bookkeeping), so hooks must not recurse into themselves.
+    static IN_HOOK: Cell<bool> = Cell::new(false);
+}
+
+static SOCKET: Mutex<Option<UnixStream>> = Mutex::new(None);
+static CONNECT_ATTEMPTED: AtomicI64 = AtomicI64::new(0);
+
+fn socket_path() -> String {
+    std::env::var("VALGRIND_TOOLKIT_INTERPOSER_SOCKET")
+        .unwrap_or_else(|_| format!("/tmp/valgrind-toolkit-{}.sock", unsafe { libc::getppid() }))
+}
+
+fn emit(event: AllocEvent) {
+    if CONNECT_ATTEMPTED.swap(1, Ordering::SeqCst) == 0 {
+        if let Ok(stream) = UnixStream::connect(socket_path()) {
+            if let Ok(mut guard) = SOCKET.lock() {
+                *guard = Some(stream);
+            }
+        }
+    }
+
+    if let Ok(mut guard) = SOCKET.lock() {
+        if let Some(stream) = guard.as_mut() {
+            let _ = stream.write_all(&event.to_bytes());
+        }
+    }
+}
+
+fn with_reentrancy_guard<F: FnOnce()>(f: F) {
+    let already_in_hook = IN_HOOK.with(|flag| flag.replace(true));
+    if !already_in_hook {
+        f();
+    }
+    IN_HOOK.with(|flag| flag.set(already_in_hook));
+}
+
+macro_rules! real_fn {
+    // Caches the resolved symbol in an `AtomicUsize` rather than behind a `Mutex`: glibc's
+    // `dlsym` can itself call `calloc` the first time it's invoked, which would re-enter this
+    // same macro expansion and deadlock on a non-reentrant `Mutex` held across the `dlsym`
+    // call. The atomic is only ever touched with a plain load/store around `dlsym`, so a
+    // concurrent reentrant resolution just redoes the (idempotent) lookup instead of
+    // blocking; `0` is used as the unresolved sentinel since a real symbol address is never 0.
+    ($name:expr, $ty:ty) => {{
+        static REAL: AtomicUsize = AtomicUsize::new(0);
+        let mut resolved = REAL.load(Ordering::Acquire);
+        if resolved == 0 {
+            let sym = libc::dlsym(libc::RTLD_NEXT, $name.as_ptr() as *const i8) as usize;
+            REAL.store(sym, Ordering::Release);
+            resolved = sym;
+        }
+        std::mem::transmute::<usize, $ty>(resolved)
+    }};
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn malloc(size: size_t) -> *mut c_void {
+    let real: unsafe extern "C" fn(size_t) -> *mut c_void = real_fn!("malloc\0", _);
+    let ptr = real(size);
+
+    if !ptr.is_null() {
+        with_reentrancy_guard(|| emit(AllocEvent::alloc(ptr as u64, size as u64)));
+    }
+
+    ptr
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn calloc(nmemb: size_t, size: size_t) -> *mut c_void {
+    let real: unsafe extern "C" fn(size_t, size_t) -> *mut c_void = real_fn!("calloc\0", _);
+    let ptr = real(nmemb, size);
+
+    if !ptr.is_null() {
+        with_reentrancy_guard(|| emit(AllocEvent::alloc(ptr as u64, (nmemb * size) as u64)));
+    }
+
+    ptr
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn realloc(ptr: *mut c_void, size: size_t) -> *mut c_void {
+    let real: unsafe extern "C" fn(*mut c_void, size_t) -> *mut c_void = real_fn!("realloc\0", _);
+
+    // realloc(NULL, n) is a pure alloc; realloc(p, 0) is a pure free. Model everything else
+    // as free-of-old followed by alloc-of-new, matching how the monitor tracks addresses.
+    if ptr.is_null() {
+        return malloc(size);
+    }
+    if size == 0 {
+        free(ptr);
+        return std::ptr::null_mut();
+    }
+
+    let old_address = ptr as u64;
+    let new_ptr = real(ptr, size);
+
+    if !new_ptr.is_null() {
+        with_reentrancy_guard(|| {
+            emit(AllocEvent::free(old_address));
+            emit(AllocEvent::alloc(new_ptr as u64, size as u64));
+        });
+    }
+
+    new_ptr
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn free(ptr: *mut c_void) {
+    let real: unsafe extern "C" fn(*mut c_void) = real_fn!("free\0", _);
+
+    // free(NULL) is a documented no-op; don't emit a spurious event for it.
+    if !ptr.is_null() {
+        with_reentrancy_guard(|| emit(AllocEvent::free(ptr as u64)));
+    }
+
+    real(ptr);
+}
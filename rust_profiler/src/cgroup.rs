@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// cgroup v2 memory and CPU accounting for a target process, read alongside the plain
+/// `/proc/<pid>` process monitor so a containerized workload reports the real
+/// control-group limits and usage rather than just the target PID's RSS.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CgroupStats {
+    pub current_bytes: usize,
+    pub peak_bytes: usize,
+    /// `None` when `memory.max` is `"max"` (no limit configured).
+    pub limit_bytes: Option<usize>,
+    pub anon_bytes: usize,
+    pub file_bytes: usize,
+    pub kernel_bytes: usize,
+    pub cpu_usage_usec: u64,
+}
+
+/// Resolve the cgroup v2 path for `pid` from `/proc/<pid>/cgroup` (the unified hierarchy
+/// line has an empty controller list: `0::/path`).
+fn resolve_cgroup_path(pid: u32) -> Result<PathBuf> {
+    let contents = std::fs::read_to_string(format!("/proc/{pid}/cgroup"))
+        .context("Failed to read /proc/<pid>/cgroup")?;
+
+    for line in contents.lines() {
+        if let Some(rest) = line.strip_prefix("0::") {
+            return Ok(PathBuf::from("/sys/fs/cgroup").join(rest.trim_start_matches('/')));
+        }
+    }
+
+    anyhow::bail!("No cgroup v2 entry found for pid {pid}")
+}
+
+fn read_u64_or_max(path: &std::path::Path) -> Option<usize> {
+    let raw = std::fs::read_to_string(path).ok()?;
+    let raw = raw.trim();
+    if raw == "max" {
+        None
+    } else {
+        raw.parse().ok()
+    }
+}
+
+fn parse_stat_file(path: &std::path::Path) -> HashMap<String, u64> {
+    let mut fields = HashMap::new();
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return fields;
+    };
+
+    for line in contents.lines() {
+        if let Some((key, value)) = line.split_once(' ') {
+            if let Ok(value) = value.trim().parse() {
+                fields.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    fields
+}
+
+/// Read `memory.current`, `memory.peak`, `memory.max`, the `memory.stat` breakdown, and
+/// `cpu.stat`'s `usage_usec` for `pid`'s cgroup. Returns `Ok(None)` instead of an error when
+/// the cgroup files aren't present (e.g. not running under cgroup v2), so callers can skip
+/// the section gracefully.
+pub fn read_cgroup_stats(pid: u32) -> Result<Option<CgroupStats>> {
+    let cgroup_path = match resolve_cgroup_path(pid) {
+        Ok(path) => path,
+        Err(_) => return Ok(None),
+    };
+
+    let current_path = cgroup_path.join("memory.current");
+    if !current_path.exists() {
+        return Ok(None);
+    }
+
+    let current_bytes = read_u64_or_max(&current_path).unwrap_or(0);
+    let peak_bytes = read_u64_or_max(&cgroup_path.join("memory.peak")).unwrap_or(current_bytes);
+    let limit_bytes = read_u64_or_max(&cgroup_path.join("memory.max"));
+
+    let memory_stat = parse_stat_file(&cgroup_path.join("memory.stat"));
+    let anon_bytes = memory_stat.get("anon").copied().unwrap_or(0) as usize;
+    let file_bytes = memory_stat.get("file").copied().unwrap_or(0) as usize;
+    let kernel_bytes = memory_stat.get("kernel").copied().unwrap_or(0) as usize;
+
+    let cpu_stat = parse_stat_file(&cgroup_path.join("cpu.stat"));
+    let cpu_usage_usec = cpu_stat.get("usage_usec").copied().unwrap_or(0);
+
+    Ok(Some(CgroupStats {
+        current_bytes,
+        peak_bytes,
+        limit_bytes,
+        anon_bytes,
+        file_bytes,
+        kernel_bytes,
+        cpu_usage_usec,
+    }))
+}
+
+/// Returns `true` when `memory.current` exceeds `fraction` of `memory.max`, signalling
+/// OOM risk to the caller. Always `false` when there is no configured limit.
+pub fn is_oom_risk(stats: &CgroupStats, fraction: f64) -> bool {
+    match stats.limit_bytes {
+        Some(limit) if limit > 0 => stats.current_bytes as f64 / limit as f64 > fraction,
+        _ => false,
+    }
+}
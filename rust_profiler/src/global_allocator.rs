@@ -0,0 +1,140 @@
+//! In-process tracking mode: link this crate into a target program and install
+//! `TrackingAllocator` as `#[global_allocator]` to capture real `alloc`/`dealloc` events
+//! instead of sampling another process's RSS from the outside. Gated behind the
+//! `global-allocator` cargo feature since it swaps the program's allocator globally.
+#![cfg(feature = "global-allocator")]
+
+use crate::memory_tracker::MemoryTracker;
+use crate::{AllocationInfo, MemoryStats};
+use once_cell::sync::Lazy;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::Mutex;
+
+static TRACKER: Lazy<Mutex<MemoryTracker>> = Lazy::new(|| Mutex::new(MemoryTracker::new()));
+
+thread_local! {
+    // Guards against reentrancy: the tracker's own bookkeeping allocations (e.g. growing
+    // the HashMap) must not recurse back into `add_allocation`.
+    static IN_TRACKER: Cell<bool> = Cell::new(false);
+}
+
+/// Wraps the system allocator (the same approach as `stats_alloc`) and records each
+/// `alloc`/`dealloc` into the shared `MemoryTracker` along with a captured backtrace. An event
+/// that arrives while the tracker's lock is held (by another thread, or by this thread inside
+/// `MemoryProfiler`) is not recorded.
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+
+        if !ptr.is_null() {
+            record_alloc(ptr as usize, layout.size());
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record_dealloc(ptr as usize);
+        System.dealloc(ptr, layout);
+    }
+}
+
+fn record_alloc(address: usize, size: usize) {
+    let already_tracking = IN_TRACKER.with(|flag| flag.replace(true));
+    if already_tracking {
+        return;
+    }
+
+    if let Ok(mut tracker) = TRACKER.try_lock() {
+        tracker.add_allocation(
+            address,
+            AllocationInfo {
+                size,
+                timestamp: chrono::Utc::now(),
+                stack_trace: capture_backtrace(),
+                thread_id: thread_id(),
+            },
+        );
+    }
+
+    IN_TRACKER.with(|flag| flag.set(false));
+}
+
+fn record_dealloc(address: usize) {
+    let already_tracking = IN_TRACKER.with(|flag| flag.replace(true));
+    if already_tracking {
+        return;
+    }
+
+    if let Ok(mut tracker) = TRACKER.try_lock() {
+        tracker.remove_allocation(address);
+    }
+
+    IN_TRACKER.with(|flag| flag.set(false));
+}
+
+fn capture_backtrace() -> Vec<String> {
+    std::backtrace::Backtrace::force_capture()
+        .to_string()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn thread_id() -> u32 {
+    // `std::thread::ThreadId` has no stable numeric representation, so hash it instead.
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    std::thread::current().id().hash(&mut hasher);
+    hasher.finish() as u32
+}
+
+/// Entry point for programs that link this crate as a library: install the allocator with
+/// `#[global_allocator] static ALLOC: TrackingAllocator = TrackingAllocator;`, then call
+/// `MemoryProfiler::start()`/`report()` to read back precise allocation counts and true
+/// leak lists (addresses never freed), rather than sampled `/proc` deltas.
+pub struct MemoryProfiler;
+
+impl MemoryProfiler {
+    /// Reset the shared tracker so a fresh measurement window begins now.
+    pub fn start() {
+        if let Ok(mut tracker) = TRACKER.lock() {
+            tracker.clear();
+        }
+    }
+
+    /// Snapshot the stats accumulated since the last `start()` call.
+    pub fn report() -> MemoryStats {
+        TRACKER
+            .lock()
+            .map(|tracker| clone_stats(tracker.get_current_stats()))
+            .unwrap_or_else(|_| empty_stats())
+    }
+}
+
+fn clone_stats(stats: &MemoryStats) -> MemoryStats {
+    MemoryStats {
+        total_allocated: stats.total_allocated,
+        total_freed: stats.total_freed,
+        current_usage: stats.current_usage,
+        peak_usage: stats.peak_usage,
+        allocation_count: stats.allocation_count,
+        free_count: stats.free_count,
+        active_allocations: stats.active_allocations.clone(),
+    }
+}
+
+fn empty_stats() -> MemoryStats {
+    MemoryStats {
+        total_allocated: 0,
+        total_freed: 0,
+        current_usage: 0,
+        peak_usage: 0,
+        allocation_count: 0,
+        free_count: 0,
+        active_allocations: std::collections::HashMap::new(),
+    }
+}
@@ -0,0 +1,184 @@
+use crate::MemoryStats;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{self, Duration};
+use tracing::{error, warn};
+
+/// One line-protocol record ready to be flushed to an InfluxDB-compatible endpoint.
+pub struct InfluxLine(String);
+
+impl InfluxLine {
+    /// Encode a single memory sample as `measurement,tag=val field=val ... <unix_nanos>`.
+    pub fn from_stats(measurement: &str, pid: u32, command: &str, stats: &MemoryStats) -> Self {
+        let ns = chrono::Utc::now()
+            .timestamp_nanos_opt()
+            .unwrap_or_default();
+
+        Self(format!(
+            "{measurement},pid={pid},command={command} current_usage={cur}i,peak_usage={peak}i,allocation_count={allocs}i,active_allocations={active}i {ns}",
+            measurement = measurement,
+            pid = pid,
+            command = escape_tag_value(command),
+            cur = stats.current_usage,
+            peak = stats.peak_usage,
+            allocs = stats.allocation_count,
+            active = stats.active_allocations.len(),
+            ns = ns,
+        ))
+    }
+}
+
+fn escape_tag_value(value: &str) -> String {
+    value.replace(' ', "\\ ").replace(',', "\\,").replace('=', "\\=")
+}
+
+/// How often the flusher batches up pending lines into one POST, independent of how often
+/// samples arrive on the channel.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+enum ExporterMsg {
+    Line(InfluxLine),
+    /// Sent by `flush()`; the flusher drains and POSTs everything still pending, then
+    /// signals completion through the embedded oneshot and keeps running.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Batches line-protocol samples onto a `tokio::sync::mpsc` channel so the sampling loop is
+/// never blocked on network I/O; a background task drains the channel, batching lines into
+/// one POST per `FLUSH_INTERVAL` tick (or on an explicit `flush()`) instead of one POST per
+/// sample.
+pub struct InfluxExporter {
+    sender: mpsc::UnboundedSender<ExporterMsg>,
+}
+
+impl InfluxExporter {
+    /// Spawn the background flusher task and return a handle for submitting samples. Runs as
+    /// a plain `tokio::spawn` task (not `spawn_blocking`): it only ever awaits the channel or
+    /// the HTTP client, so it never parks a runtime worker thread.
+    pub fn spawn(url: String, measurement: String) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<ExporterMsg>();
+
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            let _ = measurement; // measurement is embedded per-line by the caller
+            let mut batch: Vec<InfluxLine> = Vec::new();
+            let mut ticker = time::interval(FLUSH_INTERVAL);
+
+            loop {
+                tokio::select! {
+                    msg = receiver.recv() => {
+                        match msg {
+                            Some(ExporterMsg::Line(line)) => batch.push(line),
+                            Some(ExporterMsg::Flush(done)) => {
+                                flush_batch(&client, &url, &mut batch).await;
+                                let _ = done.send(());
+                            }
+                            None => break,
+                        }
+                    }
+                    _ = ticker.tick() => {
+                        flush_batch(&client, &url, &mut batch).await;
+                    }
+                }
+            }
+
+            flush_batch(&client, &url, &mut batch).await;
+        });
+
+        Self { sender }
+    }
+
+    /// Enqueue a sample; never blocks the sampling loop on network I/O.
+    pub fn push(&self, measurement: &str, pid: u32, command: &str, stats: &MemoryStats) {
+        let line = InfluxLine::from_stats(measurement, pid, command, stats);
+        if self.sender.send(ExporterMsg::Line(line)).is_err() {
+            error!("InfluxDB exporter channel closed, dropping sample");
+        }
+    }
+
+    /// Await delivery of every queued sample (call at shutdown, before the runtime is
+    /// dropped). Unlike polling the channel for emptiness, this only returns once the
+    /// flusher has actually finished its POST, so a shutdown racing the final flush can't
+    /// drop samples. If the flusher task has already exited (e.g. it panicked), this returns
+    /// immediately instead of waiting forever.
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.sender.send(ExporterMsg::Flush(done_tx)).is_err() {
+            warn!("InfluxDB exporter flusher task is gone, nothing to flush");
+            return;
+        }
+        let _ = done_rx.await;
+    }
+}
+
+async fn flush_batch(client: &reqwest::Client, url: &str, batch: &mut Vec<InfluxLine>) {
+    if batch.is_empty() {
+        return;
+    }
+
+    let body = batch.iter().map(|line| line.0.as_str()).collect::<Vec<_>>().join("\n");
+    if let Err(e) = post_batch(client, url, &body).await {
+        warn!("Failed to push {} sample(s) to InfluxDB endpoint {}: {}", batch.len(), url, e);
+    }
+    batch.clear();
+}
+
+async fn post_batch(client: &reqwest::Client, url: &str, body: &str) -> anyhow::Result<()> {
+    client
+        .post(url)
+        .body(body.to_string())
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn sample_stats() -> MemoryStats {
+        MemoryStats {
+            total_allocated: 4096,
+            total_freed: 1024,
+            current_usage: 3072,
+            peak_usage: 4096,
+            allocation_count: 7,
+            free_count: 2,
+            active_allocations: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn from_stats_encodes_measurement_tags_and_fields() {
+        let stats = sample_stats();
+        let line = InfluxLine::from_stats("memory", 1234, "my-command", &stats);
+
+        assert!(line.0.starts_with("memory,pid=1234,command=my-command "));
+        assert!(line.0.contains("current_usage=3072i"));
+        assert!(line.0.contains("peak_usage=4096i"));
+        assert!(line.0.contains("allocation_count=7i"));
+        assert!(line.0.contains("active_allocations=0i"));
+    }
+
+    #[test]
+    fn from_stats_ends_with_a_unix_nanos_timestamp() {
+        let stats = sample_stats();
+        let line = InfluxLine::from_stats("memory", 1, "cmd", &stats);
+        let ns_field = line.0.rsplit(' ').next().unwrap();
+        assert!(ns_field.parse::<i64>().is_ok());
+    }
+
+    #[test]
+    fn escape_tag_value_escapes_commas_spaces_and_equals() {
+        assert_eq!(escape_tag_value("a b"), "a\\ b");
+        assert_eq!(escape_tag_value("a,b"), "a\\,b");
+        assert_eq!(escape_tag_value("a=b"), "a\\=b");
+        assert_eq!(escape_tag_value("a,b=c d"), "a\\,b\\=c\\ d");
+    }
+
+    #[test]
+    fn escape_tag_value_leaves_plain_identifiers_untouched() {
+        assert_eq!(escape_tag_value("my-command"), "my-command");
+    }
+}
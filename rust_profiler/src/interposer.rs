@@ -0,0 +1,370 @@
+//! Real allocation tracking via the `interposer` crate (an `LD_PRELOAD`-able malloc/free
+//! shim, pulled in as a workspace path dependency) for processes the toolkit spawns itself,
+//! falling back to `ptrace` breakpoints when attaching to an already-running PID where
+//! `LD_PRELOAD` can no longer be injected.
+use crate::memory_tracker::MemoryTracker;
+use crate::AllocationInfo;
+use anyhow::{Context, Result};
+use interposer::AllocEvent;
+use std::os::unix::net::UnixListener;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Event kind byte used on the wire by the `interposer` crate.
+const KIND_ALLOC: u8 = 0;
+const KIND_FREE: u8 = 1;
+
+/// Bind the per-run event socket and return both the listener and the path the child
+/// process should be told about via `VALGRIND_TOOLKIT_INTERPOSER_SOCKET`.
+pub fn bind_event_socket(pid: u32) -> Result<(UnixListener, String)> {
+    let path = format!("/tmp/valgrind-toolkit-{pid}.sock");
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).context("Failed to bind interposer event socket")?;
+    Ok((listener, path))
+}
+
+/// Environment variables to set on a spawned child so the interposer shim (injected via
+/// `LD_PRELOAD`) knows which socket to stream events to.
+pub fn child_env(socket_path: &str, interposer_lib_path: &str) -> Vec<(String, String)> {
+    vec![
+        ("LD_PRELOAD".to_string(), interposer_lib_path.to_string()),
+        (
+            "VALGRIND_TOOLKIT_INTERPOSER_SOCKET".to_string(),
+            socket_path.to_string(),
+        ),
+    ]
+}
+
+/// Accept the interposer's connection and stream `AllocEvent`s into `tracker` until the
+/// socket closes (the target process exited or was killed).
+pub fn stream_events(listener: UnixListener, tracker: Arc<Mutex<MemoryTracker>>) {
+    std::thread::spawn(move || {
+        let (mut stream, _addr) = match listener.accept() {
+            Ok(pair) => pair,
+            Err(e) => {
+                warn!("Interposer never connected: {}", e);
+                return;
+            }
+        };
+
+        let mut buf = [0u8; AllocEvent::WIRE_SIZE];
+        loop {
+            use std::io::Read;
+            match stream.read_exact(&mut buf) {
+                Ok(()) => apply_event(&buf, &tracker),
+                Err(_) => break, // socket closed: target exited
+            }
+        }
+    });
+}
+
+fn apply_event(buf: &[u8; AllocEvent::WIRE_SIZE], tracker: &Arc<Mutex<MemoryTracker>>) {
+    let kind = buf[0];
+    let address = u64::from_ne_bytes(buf[8..16].try_into().unwrap()) as usize;
+    let size = u64::from_ne_bytes(buf[16..24].try_into().unwrap()) as usize;
+    let timestamp_nanos = i64::from_ne_bytes(buf[24..32].try_into().unwrap());
+    let thread_id = u64::from_ne_bytes(buf[32..40].try_into().unwrap()) as u32;
+
+    let Ok(mut tracker) = tracker.lock() else {
+        return;
+    };
+
+    match kind {
+        KIND_ALLOC => tracker.add_allocation(
+            address,
+            AllocationInfo {
+                size,
+                timestamp: chrono::DateTime::from_timestamp_nanos(timestamp_nanos),
+                stack_trace: Vec::new(),
+                thread_id,
+            },
+        ),
+        KIND_FREE => {
+            tracker.remove_allocation(address);
+        }
+        _ => {}
+    }
+}
+
+/// Attach-to-running-pid fallback: since `LD_PRELOAD` only takes effect at process start,
+/// an already-running target must instead be tracked with `ptrace` breakpoints planted on
+/// `malloc`/`calloc`/`realloc`/`free`. This mirrors the interposer's event model (alloc/free
+/// by address) but drives it by trapping the target's own libc calls instead of linking a
+/// shim into it.
+pub mod ptrace_fallback {
+    use super::*;
+    use nix::sys::ptrace;
+    use nix::sys::wait::waitpid;
+    use nix::unistd::Pid;
+
+    /// Resolved breakpoint addresses for the four hooked libc entry points, found by
+    /// reading the target's `/proc/<pid>/maps` to locate its loaded libc, then parsing that
+    /// libc's ELF dynamic symbol table for `malloc`/`calloc`/`realloc`/`free`.
+    pub struct HookAddresses {
+        pub malloc: u64,
+        pub calloc: u64,
+        pub realloc: u64,
+        pub free: u64,
+    }
+
+    /// Locate the target's libc mapping and resolve the four hook symbols within it.
+    pub fn resolve_hook_addresses(pid: u32) -> Result<HookAddresses> {
+        let maps = std::fs::read_to_string(format!("/proc/{pid}/maps"))
+            .context("Failed to read /proc/<pid>/maps")?;
+
+        let libc_line = maps
+            .lines()
+            .find(|line| line.contains("libc.so") || line.contains("libc-"))
+            .context("Target process has no libc mapping")?;
+
+        let mut parts = libc_line.split_whitespace();
+        let range = parts.next().context("Malformed maps line")?;
+        let (start_str, _end_str) = range
+            .split_once('-')
+            .context("Malformed address range in maps line")?;
+        let base = u64::from_str_radix(start_str, 16).context("Malformed base address")?;
+        let libc_path = libc_line
+            .split_whitespace()
+            .last()
+            .context("maps line missing pathname")?;
+
+        let file = std::fs::read(libc_path).context("Failed to read target's libc from disk")?;
+        let elf = goblin::elf::Elf::parse(&file).context("Failed to parse libc ELF")?;
+
+        let resolve = |name: &str| -> Result<u64> {
+            elf.dynsyms
+                .iter()
+                .find(|sym| elf.dynstrtab.get_at(sym.st_name) == Some(name))
+                .map(|sym| base + sym.st_value)
+                .context(format!("Symbol {name} not found in target's libc"))
+        };
+
+        Ok(HookAddresses {
+            malloc: resolve("malloc")?,
+            calloc: resolve("calloc")?,
+            realloc: resolve("realloc")?,
+            free: resolve("free")?,
+        })
+    }
+
+    /// A single planted breakpoint: the original instruction byte we overwrote with `0xCC`
+    /// (`int3`), restored once the trap is hit and single-stepped past.
+    pub struct Breakpoint {
+        pub address: u64,
+        pub original_byte: i64,
+    }
+
+    /// Overwrite the first byte at `address` with `int3`, saving the original so the call
+    /// can be single-stepped past it once the trap fires.
+    pub fn plant_breakpoint(pid: Pid, address: u64) -> Result<Breakpoint> {
+        let word = ptrace::read(pid, address as ptrace::AddressType)
+            .context("Failed to read instruction word for breakpoint")?;
+        let original_byte = word & 0xff;
+        let trapped = (word & !0xff) | 0xCC;
+
+        unsafe {
+            ptrace::write(pid, address as ptrace::AddressType, trapped)
+                .context("Failed to plant breakpoint")?;
+        }
+
+        Ok(Breakpoint { address, original_byte })
+    }
+
+    /// Restore the original instruction byte so the trapped call can run to completion.
+    pub fn remove_breakpoint(pid: Pid, bp: &Breakpoint) -> Result<()> {
+        let word = ptrace::read(pid, bp.address as ptrace::AddressType)
+            .context("Failed to read instruction word when restoring breakpoint")?;
+        let restored = (word & !0xff) | bp.original_byte;
+
+        unsafe {
+            ptrace::write(pid, bp.address as ptrace::AddressType, restored)
+                .context("Failed to restore original instruction")?;
+        }
+
+        Ok(())
+    }
+
+    /// Which hooked allocation function an entry `int3` belonged to, with the arguments
+    /// captured at entry (the requested size, and for `realloc` the old pointer), held until
+    /// its return breakpoint fires and the resulting pointer can be read.
+    enum PendingCall {
+        Malloc { size: u64 },
+        Calloc { size: u64 },
+        Realloc { old_address: u64, size: u64 },
+    }
+
+    /// Attach to `pid`, plant breakpoints on `malloc`/`calloc`/`realloc`/`free`, and drive a
+    /// blocking wait/singlestep loop that turns each trapped call into an `add_allocation`/
+    /// `remove_allocation` against `tracker`. Runs until the target exits or a ptrace call
+    /// fails (e.g. a breakpoint can no longer be re-armed).
+    ///
+    /// Caveat: the x86-64 System V ABI guarantees the return address sits at `[rsp]` on
+    /// function entry, which this relies on to plant a matching return breakpoint for
+    /// `malloc`/`calloc`/`realloc` — the entry `int3` must be the very first byte executed,
+    /// before any prologue (e.g. a CET `endbr64`) touches the stack.
+    pub fn run_tracking_loop(pid: Pid, tracker: Arc<Mutex<MemoryTracker>>) -> Result<()> {
+        ptrace::attach(pid).context("Failed to ptrace-attach to target")?;
+        waitpid(pid, None).context("Failed waiting for initial stop after attach")?;
+
+        let hooks = resolve_hook_addresses(pid.as_raw() as u32)?;
+        let malloc_bp = plant_breakpoint(pid, hooks.malloc)?;
+        let calloc_bp = plant_breakpoint(pid, hooks.calloc)?;
+        let realloc_bp = plant_breakpoint(pid, hooks.realloc)?;
+        let free_bp = plant_breakpoint(pid, hooks.free)?;
+
+        // Keyed by the entry `rsp` (the address, on that thread's stack, holding the return
+        // address at function entry) rather than the return address itself: two outstanding
+        // calls from the same call site (recursion, or concurrent calls on different threads)
+        // share a return address but never share an `rsp`, so this key stays unique per call.
+        let mut pending: std::collections::HashMap<u64, PendingCall> = std::collections::HashMap::new();
+        // Return breakpoints are shared by every outstanding call to the same call site, so
+        // each entry carries an arm count alongside the breakpoint — the trap is only fully
+        // removed once the last outstanding call to that site has returned.
+        let mut return_bps: std::collections::HashMap<u64, (Breakpoint, usize)> = std::collections::HashMap::new();
+
+        ptrace::cont(pid, None).context("Failed to resume target after attach")?;
+
+        loop {
+            use nix::sys::wait::WaitStatus;
+            let status = waitpid(pid, None).context("Failed waiting for trap")?;
+            match status {
+                WaitStatus::Stopped(_, nix::sys::signal::Signal::SIGTRAP) => {}
+                WaitStatus::Stopped(_, signal) => {
+                    // A signal meant for the target rather than one of our traps: deliver it
+                    // and keep tracking.
+                    ptrace::cont(pid, Some(signal)).context("Failed to resume target with its signal")?;
+                    continue;
+                }
+                _ => break,
+            }
+
+            let mut regs = ptrace::getregs(pid).context("Failed to read registers")?;
+            let trap_address = regs.rip - 1; // rip is past the int3 byte
+
+            if trap_address == hooks.malloc {
+                pending.insert(regs.rsp, PendingCall::Malloc { size: regs.rdi });
+                arm_return_breakpoint(pid, regs.rsp, &mut return_bps)?;
+                restep_past(pid, &malloc_bp, &mut regs)?;
+            } else if trap_address == hooks.calloc {
+                let size = regs.rdi * regs.rsi;
+                pending.insert(regs.rsp, PendingCall::Calloc { size });
+                arm_return_breakpoint(pid, regs.rsp, &mut return_bps)?;
+                restep_past(pid, &calloc_bp, &mut regs)?;
+            } else if trap_address == hooks.realloc {
+                pending.insert(regs.rsp, PendingCall::Realloc { old_address: regs.rdi, size: regs.rsi });
+                arm_return_breakpoint(pid, regs.rsp, &mut return_bps)?;
+                restep_past(pid, &realloc_bp, &mut regs)?;
+            } else if trap_address == hooks.free {
+                if let Ok(mut t) = tracker.lock() {
+                    t.remove_allocation(regs.rdi as usize);
+                }
+                restep_past(pid, &free_bp, &mut regs)?;
+            } else if return_bps.contains_key(&trap_address) {
+                // `ret` has already popped the return address off the stack by the time
+                // control reaches it, so the entry `rsp` we keyed `pending` on is `rsp - 8`.
+                let entry_rsp = regs.rsp.wrapping_sub(8);
+                if let Some(call) = pending.remove(&entry_rsp) {
+                    let returned_address = regs.rax;
+                    record_call(&tracker, call, returned_address);
+                }
+                retire_return_breakpoint(pid, trap_address, &mut regs, &mut return_bps)?;
+            } else {
+                // Unknown trap (e.g. a real breakpoint/signal in the target); just resume.
+            }
+
+            ptrace::cont(pid, None).context("Failed to resume target after handling trap")?;
+        }
+
+        Ok(())
+    }
+
+    fn record_call(tracker: &Arc<Mutex<MemoryTracker>>, call: PendingCall, returned_address: u64) {
+        if returned_address == 0 {
+            return; // allocation failed, nothing to track
+        }
+
+        let size = match call {
+            PendingCall::Malloc { size } | PendingCall::Calloc { size } => size,
+            PendingCall::Realloc { old_address, size } => {
+                if let Ok(mut t) = tracker.lock() {
+                    t.remove_allocation(old_address as usize);
+                }
+                size
+            }
+        };
+
+        if let Ok(mut t) = tracker.lock() {
+            t.add_allocation(
+                returned_address as usize,
+                AllocationInfo {
+                    size: size as usize,
+                    timestamp: chrono::Utc::now(),
+                    stack_trace: Vec::new(),
+                    thread_id: 0,
+                },
+            );
+        }
+    }
+
+    fn stack_return_address(pid: Pid, rsp: u64) -> Result<u64> {
+        Ok(ptrace::read(pid, rsp as ptrace::AddressType).context("Failed to read return address")? as u64)
+    }
+
+    fn arm_return_breakpoint(
+        pid: Pid,
+        rsp: u64,
+        return_bps: &mut std::collections::HashMap<u64, (Breakpoint, usize)>,
+    ) -> Result<()> {
+        let return_address = stack_return_address(pid, rsp)?;
+        match return_bps.get_mut(&return_address) {
+            Some((_, count)) => *count += 1,
+            None => {
+                let bp = plant_breakpoint(pid, return_address)?;
+                return_bps.insert(return_address, (bp, 1));
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a trapped return: rewind `rip` back to the trap address, single-step the
+    /// restored instruction byte so the tracee isn't corrupted, then either replant the
+    /// breakpoint (another call is still outstanding to this same return address) or drop it
+    /// for good (this was the last one).
+    fn retire_return_breakpoint(
+        pid: Pid,
+        trap_address: u64,
+        regs: &mut nix::libc::user_regs_struct,
+        return_bps: &mut std::collections::HashMap<u64, (Breakpoint, usize)>,
+    ) -> Result<()> {
+        let (bp, count) = return_bps.get_mut(&trap_address).expect("return breakpoint must be armed");
+        *count -= 1;
+        let remaining = *count;
+
+        remove_breakpoint(pid, bp)?;
+        regs.rip = trap_address;
+        ptrace::setregs(pid, *regs).context("Failed to rewind instruction pointer after return breakpoint")?;
+        ptrace::step(pid, None).context("Failed to single-step restored return instruction")?;
+        waitpid(pid, None).context("Failed waiting for single-step to land after return breakpoint")?;
+
+        if remaining == 0 {
+            return_bps.remove(&trap_address);
+        } else {
+            let replanted = plant_breakpoint(pid, trap_address)?;
+            return_bps.get_mut(&trap_address).unwrap().0 = replanted;
+        }
+
+        Ok(())
+    }
+
+    /// Temporarily remove a breakpoint so its original instruction can execute once, then
+    /// replant it so the *next* call to the same function traps again.
+    fn restep_past(pid: Pid, bp: &Breakpoint, regs: &mut nix::libc::user_regs_struct) -> Result<()> {
+        remove_breakpoint(pid, bp)?;
+        regs.rip = bp.address;
+        ptrace::setregs(pid, *regs).context("Failed to rewind instruction pointer")?;
+        ptrace::step(pid, None).context("Failed to single-step original instruction")?;
+        waitpid(pid, None).context("Failed waiting for single-step to land")?;
+        plant_breakpoint(pid, bp.address)?;
+        Ok(())
+    }
+}
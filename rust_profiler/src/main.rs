@@ -8,15 +8,29 @@ use std::time::{Duration, Instant};
 use tokio::time;
 use tracing::{error, info, warn};
 
+mod cgroup;
+#[cfg(feature = "global-allocator")]
+mod global_allocator;
+mod influx_export;
+mod interposer;
+#[cfg(feature = "jemalloc-profiling")]
+mod jemalloc_profiling;
+mod memory_reporter;
+mod memory_sampler;
 mod memory_tracker;
 mod process_monitor;
 mod report_generator;
 
+use cgroup::CgroupStats;
+use influx_export::InfluxExporter;
+use memory_reporter::MemoryReport;
+use memory_sampler::MemorySampler;
 use memory_tracker::MemoryTracker;
 use process_monitor::ProcessMonitor;
 use report_generator::ReportGenerator;
+use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AllocationInfo {
     pub size: usize,
     pub timestamp: chrono::DateTime<chrono::Utc>,
@@ -35,6 +49,20 @@ pub struct MemoryStats {
     pub active_allocations: HashMap<usize, AllocationInfo>,
 }
 
+/// A point-in-time snapshot of the scalar fields of `MemoryStats`, recorded on every
+/// `MemoryTracker::update_stats` call so the run's trajectory is preserved instead of only
+/// the final endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub current_usage: usize,
+    pub peak_usage: usize,
+    pub total_allocated: usize,
+    pub allocation_count: u64,
+    pub active_allocations: usize,
+    pub cpu_percent: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProfileReport {
     pub pid: u32,
@@ -44,6 +72,41 @@ pub struct ProfileReport {
     pub duration: Duration,
     pub memory_stats: MemoryStats,
     pub leak_summary: LeakSummary,
+    pub samples: Vec<Snapshot>,
+    pub cgroup_stats: Option<CgroupStats>,
+    pub memory_profile: MemoryProfile,
+    pub memory_report: MemoryReport,
+}
+
+/// Distribution of RSS occupancy over the process's lifetime, collected by `MemorySampler` on
+/// its own background poll loop independent of the profiler's sampling interval. Complements
+/// `MemoryStats`/`Snapshot`, which only capture point-in-time/periodic values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryProfile {
+    pub sample_count: u64,
+    pub min_rss: usize,
+    pub max_rss: usize,
+    pub mean_rss: f64,
+    pub high_water_mark: usize,
+    /// Non-empty `(bucket_low, bucket_high, count)` rows, geometric-bucket boundaries.
+    pub histogram: Vec<(usize, usize, u64)>,
+    /// Samples whose RSS exceeded the histogram's largest bucket boundary.
+    pub overflow_count: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeHistogramMode {
+    Exact,
+    Log2,
+}
+
+impl SizeHistogramMode {
+    fn parse(value: &str) -> Self {
+        match value {
+            "exact" => Self::Exact,
+            _ => Self::Log2,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,6 +115,9 @@ pub struct LeakSummary {
     pub leak_count: usize,
     pub largest_leak: Option<usize>,
     pub leaks_by_size: Vec<(usize, usize)>, // (size, count)
+    /// Populated instead of `leaks_by_size` when `--size-histogram=log2`: one row per
+    /// ceil-power-of-two bucket as `(bucket_low, bucket_high, count, bytes)`.
+    pub leaks_by_bucket: Vec<(u64, u64, usize, usize)>,
 }
 
 #[tokio::main]
@@ -106,6 +172,53 @@ async fn main() -> Result<()> {
                 .help("Show live memory statistics")
                 .takes_value(false),
         )
+        .arg(
+            Arg::new("influx-url")
+                .long("influx-url")
+                .value_name("URL")
+                .help("Stream each sample to an InfluxDB-compatible write endpoint"),
+        )
+        .arg(
+            Arg::new("influx-measurement")
+                .long("influx-measurement")
+                .value_name("NAME")
+                .help("Measurement name used for InfluxDB line-protocol records")
+                .default_value("mem_profile"),
+        )
+        .arg(
+            Arg::new("size-histogram")
+                .long("size-histogram")
+                .value_name("MODE")
+                .help("Group leaks by exact byte size or log2 (power-of-two) bucket")
+                .possible_values(["exact", "log2"])
+                .default_value("log2"),
+        )
+        .arg(
+            Arg::new("cgroup")
+                .long("cgroup")
+                .help("Report cgroup v2 memory/CPU accounting alongside the process stats")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("cgroup-oom-threshold")
+                .long("cgroup-oom-threshold")
+                .value_name("FRACTION")
+                .help("Warn when memory.current exceeds this fraction of memory.max")
+                .default_value("0.9"),
+        )
+        .arg(
+            Arg::new("track-allocations")
+                .long("track-allocations")
+                .help("Track real malloc/free events (LD_PRELOAD interposer for spawned commands, ptrace breakpoints for --pid)")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("interposer-lib")
+                .long("interposer-lib")
+                .value_name("PATH")
+                .help("Path to the compiled interposer shared library")
+                .default_value("./target/release/libinterposer.so"),
+        )
         .get_matches();
 
     let output_file = matches.value_of("output").unwrap();
@@ -120,13 +233,54 @@ async fn main() -> Result<()> {
         .parse::<u64>()
         .context("Invalid duration")?;
     let live_mode = matches.is_present("live");
+    let influx_measurement = matches.value_of("influx-measurement").unwrap().to_string();
+    let influx_exporter = matches
+        .value_of("influx-url")
+        .map(|url| InfluxExporter::spawn(url.to_string(), influx_measurement.clone()));
+    let size_histogram_mode = SizeHistogramMode::parse(matches.value_of("size-histogram").unwrap());
+    let cgroup_enabled = matches.is_present("cgroup");
+    let cgroup_oom_threshold = matches
+        .value_of("cgroup-oom-threshold")
+        .unwrap()
+        .parse::<f64>()
+        .context("Invalid cgroup OOM threshold")?;
+
+    let track_allocations = matches.is_present("track-allocations");
+    let interposer_lib = matches.value_of("interposer-lib").unwrap().to_string();
 
     if let Some(pid_str) = matches.value_of("pid") {
         let pid = pid_str.parse::<u32>().context("Invalid PID")?;
-        profile_existing_process(pid, output_file, interval, max_duration, live_mode).await?;
+        profile_existing_process(
+            pid,
+            output_file,
+            interval,
+            max_duration,
+            live_mode,
+            influx_exporter,
+            &influx_measurement,
+            size_histogram_mode,
+            cgroup_enabled,
+            cgroup_oom_threshold,
+            track_allocations,
+        )
+        .await?;
     } else if let Some(command) = matches.values_of("command") {
         let cmd_args: Vec<&str> = command.collect();
-        profile_new_process(&cmd_args, output_file, interval, max_duration, live_mode).await?;
+        profile_new_process(
+            &cmd_args,
+            output_file,
+            interval,
+            max_duration,
+            live_mode,
+            influx_exporter,
+            &influx_measurement,
+            size_histogram_mode,
+            cgroup_enabled,
+            cgroup_oom_threshold,
+            track_allocations,
+            &interposer_lib,
+        )
+        .await?;
     } else {
         eprintln!("Error: Must specify either --pid or a command to run");
         std::process::exit(1);
@@ -141,6 +295,12 @@ async fn profile_existing_process(
     interval: u64,
     max_duration: u64,
     live_mode: bool,
+    influx_exporter: Option<InfluxExporter>,
+    influx_measurement: &str,
+    size_histogram_mode: SizeHistogramMode,
+    cgroup_enabled: bool,
+    cgroup_oom_threshold: f64,
+    track_allocations: bool,
 ) -> Result<()> {
     info!("Profiling existing process PID: {}", pid);
 
@@ -148,6 +308,26 @@ async fn profile_existing_process(
     let mut tracker = MemoryTracker::new();
     let start_time = chrono::Utc::now();
     let start_instant = Instant::now();
+    let command = monitor.get_command_line().unwrap_or_default();
+    let mut prev_cpu_sample: Option<(u64, Instant)> = None;
+    let memory_sampler = MemorySampler::spawn(pid, Duration::from_secs(interval));
+
+    // LD_PRELOAD only applies at process start, so an already-running target is tracked
+    // with the ptrace breakpoint fallback instead of the interposer shim.
+    let allocation_tracker = if track_allocations {
+        let shared = Arc::new(Mutex::new(MemoryTracker::new()));
+        let ptrace_tracker = shared.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) =
+                interposer::ptrace_fallback::run_tracking_loop(nix::unistd::Pid::from_raw(pid as i32), ptrace_tracker)
+            {
+                warn!("ptrace allocation tracking stopped: {}", e);
+            }
+        });
+        Some(shared)
+    } else {
+        None
+    };
 
     let mut interval_timer = time::interval(Duration::from_secs(interval));
     let timeout = Duration::from_secs(max_duration);
@@ -156,13 +336,18 @@ async fn profile_existing_process(
         tokio::select! {
             _ = interval_timer.tick() => {
                 if let Ok(stats) = monitor.get_memory_stats().await {
-                    tracker.update_stats(stats);
-                    
+                    let cpu_percent = sample_cpu_percent(&monitor, &mut prev_cpu_sample);
+                    tracker.update_stats(stats, cpu_percent);
+
+                    if let Some(exporter) = &influx_exporter {
+                        exporter.push(influx_measurement, pid, &command, tracker.get_current_stats());
+                    }
+
                     if live_mode {
-                        print_live_stats(&tracker.get_current_stats());
+                        print_live_stats(&tracker.get_current_stats(), tracker.get_current_cpu_percent());
                     }
                 }
-                
+
                 if start_instant.elapsed() >= timeout {
                     warn!("Maximum duration reached, stopping profiling");
                     break;
@@ -182,14 +367,29 @@ async fn profile_existing_process(
     }
 
     let end_time = chrono::Utc::now();
-    let command = monitor.get_command_line()?;
+
+    if let Some(exporter) = &influx_exporter {
+        exporter.flush().await;
+    }
+
+    let samples = tracker.get_history().to_vec();
+    let cgroup_stats = read_cgroup_stats_if_enabled(pid, cgroup_enabled, cgroup_oom_threshold);
+    let memory_stats = merge_allocation_tracker(tracker.get_final_stats(), allocation_tracker);
+    let high_water_mark = monitor.high_water_mark().unwrap_or(memory_stats.peak_usage);
+    let memory_profile = memory_sampler.finish(high_water_mark);
+    let memory_report = monitor.get_memory_report();
 
     generate_report(
         pid,
         command,
         start_time,
         end_time,
-        tracker.get_final_stats(),
+        memory_stats,
+        samples,
+        size_histogram_mode,
+        cgroup_stats,
+        memory_profile,
+        memory_report,
         output_file,
     )
     .await?;
@@ -203,19 +403,48 @@ async fn profile_new_process(
     interval: u64,
     max_duration: u64,
     live_mode: bool,
+    influx_exporter: Option<InfluxExporter>,
+    influx_measurement: &str,
+    size_histogram_mode: SizeHistogramMode,
+    cgroup_enabled: bool,
+    cgroup_oom_threshold: f64,
+    track_allocations: bool,
+    interposer_lib: &str,
 ) -> Result<()> {
     info!("Starting new process: {:?}", cmd_args);
 
-    let mut child = tokio::process::Command::new(cmd_args[0])
-        .args(&cmd_args[1..])
-        .spawn()
-        .context("Failed to start process")?;
+    let mut command_builder = tokio::process::Command::new(cmd_args[0]);
+    command_builder.args(&cmd_args[1..]);
+
+    let allocation_tracker = if track_allocations {
+        match interposer::bind_event_socket(std::process::id()) {
+            Ok((listener, socket_path)) => {
+                let shared = Arc::new(Mutex::new(MemoryTracker::new()));
+                interposer::stream_events(listener, shared.clone());
+                for (key, value) in interposer::child_env(&socket_path, interposer_lib) {
+                    command_builder.env(key, value);
+                }
+                Some(shared)
+            }
+            Err(e) => {
+                warn!("Failed to start allocation tracking, falling back to RSS sampling: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut child = command_builder.spawn().context("Failed to start process")?;
 
     let pid = child.id().context("Failed to get process ID")?;
     let monitor = ProcessMonitor::new(pid)?;
     let mut tracker = MemoryTracker::new();
     let start_time = chrono::Utc::now();
     let start_instant = Instant::now();
+    let command = cmd_args.join(" ");
+    let mut prev_cpu_sample: Option<(u64, Instant)> = None;
+    let memory_sampler = MemorySampler::spawn(pid, Duration::from_secs(interval));
 
     let mut interval_timer = time::interval(Duration::from_secs(interval));
     let timeout = Duration::from_secs(max_duration);
@@ -224,13 +453,18 @@ async fn profile_new_process(
         tokio::select! {
             _ = interval_timer.tick() => {
                 if let Ok(stats) = monitor.get_memory_stats().await {
-                    tracker.update_stats(stats);
-                    
+                    let cpu_percent = sample_cpu_percent(&monitor, &mut prev_cpu_sample);
+                    tracker.update_stats(stats, cpu_percent);
+
+                    if let Some(exporter) = &influx_exporter {
+                        exporter.push(influx_measurement, pid, &command, tracker.get_current_stats());
+                    }
+
                     if live_mode {
-                        print_live_stats(&tracker.get_current_stats());
+                        print_live_stats(&tracker.get_current_stats(), tracker.get_current_cpu_percent());
                     }
                 }
-                
+
                 if start_instant.elapsed() >= timeout {
                     warn!("Maximum duration reached, terminating process");
                     let _ = child.kill().await;
@@ -258,14 +492,29 @@ async fn profile_new_process(
     }
 
     let end_time = chrono::Utc::now();
-    let command = cmd_args.join(" ");
+
+    if let Some(exporter) = &influx_exporter {
+        exporter.flush().await;
+    }
+
+    let samples = tracker.get_history().to_vec();
+    let cgroup_stats = read_cgroup_stats_if_enabled(pid, cgroup_enabled, cgroup_oom_threshold);
+    let memory_stats = merge_allocation_tracker(tracker.get_final_stats(), allocation_tracker);
+    let high_water_mark = monitor.high_water_mark().unwrap_or(memory_stats.peak_usage);
+    let memory_profile = memory_sampler.finish(high_water_mark);
+    let memory_report = monitor.get_memory_report();
 
     generate_report(
         pid,
         command,
         start_time,
         end_time,
-        tracker.get_final_stats(),
+        memory_stats,
+        samples,
+        size_histogram_mode,
+        cgroup_stats,
+        memory_profile,
+        memory_report,
         output_file,
     )
     .await?;
@@ -273,13 +522,97 @@ async fn profile_new_process(
     Ok(())
 }
 
-fn print_live_stats(stats: &MemoryStats) {
+fn read_cgroup_stats_if_enabled(pid: u32, enabled: bool, oom_threshold: f64) -> Option<CgroupStats> {
+    if !enabled {
+        return None;
+    }
+
+    match cgroup::read_cgroup_stats(pid) {
+        Ok(Some(stats)) => {
+            if cgroup::is_oom_risk(&stats, oom_threshold) {
+                warn!(
+                    "cgroup memory.current exceeds {:.0}% of memory.max — OOM risk",
+                    oom_threshold * 100.0
+                );
+            }
+            Some(stats)
+        }
+        Ok(None) => {
+            warn!("--cgroup was passed but no cgroup v2 files were found for pid {}", pid);
+            None
+        }
+        Err(e) => {
+            warn!("Failed to read cgroup stats for pid {}: {}", pid, e);
+            None
+        }
+    }
+}
+
+/// Overlay the interposer/ptrace-derived allocation counters onto the RSS-sampled
+/// `memory_stats`, when allocation tracking was enabled. The tracker runs on its own
+/// thread/task and is shared via `Arc<Mutex<_>>`, so it's read through `get_current_stats`
+/// rather than consumed — `Arc::try_unwrap` would fail while the tracking task still holds
+/// its clone of the handle.
+fn merge_allocation_tracker(
+    memory_stats: MemoryStats,
+    allocation_tracker: Option<Arc<Mutex<MemoryTracker>>>,
+) -> MemoryStats {
+    let Some(tracker) = allocation_tracker else {
+        return memory_stats;
+    };
+
+    let Ok(guard) = tracker.lock() else {
+        warn!("allocation tracker lock was poisoned, falling back to RSS-derived stats");
+        return memory_stats;
+    };
+
+    let tracked = guard.get_current_stats();
+    MemoryStats {
+        total_allocated: tracked.total_allocated,
+        total_freed: tracked.total_freed,
+        current_usage: tracked.current_usage,
+        peak_usage: memory_stats.peak_usage.max(tracked.peak_usage),
+        allocation_count: tracked.allocation_count,
+        free_count: tracked.free_count,
+        active_allocations: tracked.active_allocations.clone(),
+    }
+}
+
+/// Compute CPU utilization since the previous sample from cumulative `utime+stime`
+/// jiffies and the wall-clock delta. The first sample (no previous jiffies) has nothing to
+/// diff against, so it reports `0.0`; a zero time delta is guarded the same way.
+fn sample_cpu_percent(monitor: &ProcessMonitor, prev: &mut Option<(u64, Instant)>) -> f64 {
+    let Ok(jiffies) = monitor.get_cpu_jiffies() else {
+        return 0.0;
+    };
+    let now = Instant::now();
+
+    let cpu_percent = match *prev {
+        Some((prev_jiffies, prev_instant)) => {
+            let delta_seconds = now.duration_since(prev_instant).as_secs_f64();
+            if delta_seconds <= 0.0 {
+                0.0
+            } else {
+                let delta_jiffies = jiffies.saturating_sub(prev_jiffies) as f64;
+                let clk_tck = ProcessMonitor::cpu_clock_ticks_per_sec() as f64;
+                100.0 * (delta_jiffies / clk_tck) / delta_seconds
+            }
+        }
+        None => 0.0,
+    };
+
+    *prev = Some((jiffies, now));
+    cpu_percent
+}
+
+fn print_live_stats(stats: &MemoryStats, cpu_percent: f64) {
     println!("\r\x1b[2K=== Live Memory Stats ===");
     println!("Current Usage: {} KB", stats.current_usage / 1024);
     println!("Peak Usage: {} KB", stats.peak_usage / 1024);
     println!("Total Allocated: {} KB", stats.total_allocated / 1024);
     println!("Allocations: {}", stats.allocation_count);
     println!("Active Allocations: {}", stats.active_allocations.len());
+    println!("CPU Usage: {:.1}%", cpu_percent);
     println!("========================");
 }
 
@@ -289,6 +622,11 @@ async fn generate_report(
     start_time: chrono::DateTime<chrono::Utc>,
     end_time: chrono::DateTime<chrono::Utc>,
     memory_stats: MemoryStats,
+    samples: Vec<Snapshot>,
+    size_histogram_mode: SizeHistogramMode,
+    cgroup_stats: Option<CgroupStats>,
+    memory_profile: MemoryProfile,
+    memory_report: MemoryReport,
     output_file: &str,
 ) -> Result<()> {
     let duration = end_time
@@ -296,7 +634,7 @@ async fn generate_report(
         .to_std()
         .unwrap_or(Duration::ZERO);
 
-    let leak_summary = calculate_leak_summary(&memory_stats);
+    let leak_summary = calculate_leak_summary(&memory_stats, size_histogram_mode);
 
     let report = ProfileReport {
         pid,
@@ -306,6 +644,10 @@ async fn generate_report(
         duration,
         memory_stats,
         leak_summary,
+        samples,
+        cgroup_stats,
+        memory_profile,
+        memory_report,
     };
 
     // Generate JSON report
@@ -321,7 +663,7 @@ async fn generate_report(
     Ok(())
 }
 
-fn calculate_leak_summary(stats: &MemoryStats) -> LeakSummary {
+fn calculate_leak_summary(stats: &MemoryStats, mode: SizeHistogramMode) -> LeakSummary {
     let total_leaked_bytes = stats.current_usage;
     let leak_count = stats.active_allocations.len();
     let largest_leak = stats
@@ -330,19 +672,141 @@ fn calculate_leak_summary(stats: &MemoryStats) -> LeakSummary {
         .map(|alloc| alloc.size)
         .max();
 
-    // Group leaks by size
-    let mut size_groups: HashMap<usize, usize> = HashMap::new();
-    for alloc in stats.active_allocations.values() {
-        *size_groups.entry(alloc.size).or_insert(0) += 1;
-    }
+    let (leaks_by_size, leaks_by_bucket) = match mode {
+        SizeHistogramMode::Exact => {
+            // Group leaks by exact size
+            let mut size_groups: HashMap<usize, usize> = HashMap::new();
+            for alloc in stats.active_allocations.values() {
+                *size_groups.entry(alloc.size).or_insert(0) += 1;
+            }
 
-    let mut leaks_by_size: Vec<(usize, usize)> = size_groups.into_iter().collect();
-    leaks_by_size.sort_by(|a, b| b.0.cmp(&a.0)); // Sort by size, largest first
+            let mut leaks_by_size: Vec<(usize, usize)> = size_groups.into_iter().collect();
+            leaks_by_size.sort_by(|a, b| b.0.cmp(&a.0)); // Sort by size, largest first
+            (leaks_by_size, Vec::new())
+        }
+        SizeHistogramMode::Log2 => (Vec::new(), bucket_leaks_log2(stats)),
+    };
 
     LeakSummary {
         total_leaked_bytes,
         leak_count,
         largest_leak,
         leaks_by_size,
+        leaks_by_bucket,
+    }
+}
+
+/// Group leaks into logarithmic (ceil-power-of-two) buckets so real programs with
+/// thousands of distinct exact sizes still get a readable size breakdown.
+fn bucket_leaks_log2(stats: &MemoryStats) -> Vec<(u64, u64, usize, usize)> {
+    let mut buckets: HashMap<u32, (usize, usize)> = HashMap::new();
+
+    for alloc in stats.active_allocations.values() {
+        let size = alloc.size as u64;
+        let bucket = if size == 0 {
+            0
+        } else {
+            64 - (size - 1).leading_zeros()
+        };
+        let entry = buckets.entry(bucket).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += alloc.size;
+    }
+
+    let mut leaks_by_bucket: Vec<(u64, u64, usize, usize)> = buckets
+        .into_iter()
+        .map(|(bucket, (count, bytes))| {
+            // `bucket` holds sizes in `(2^(bucket-1), 2^bucket]` (size 0 is folded into
+            // bucket 0 alongside size 1). Rendered as the half-open `[lo, hi)` below, so the
+            // bounds are shifted up by one from the bucket's power-of-two boundaries —
+            // otherwise a size exactly on a power of two (e.g. 4096 in bucket 12) would be
+            // excluded from its own displayed range.
+            let (lo, hi) = if bucket == 0 {
+                (0u64, 2u64)
+            } else {
+                ((1u64 << (bucket - 1)) + 1, (1u64 << bucket) + 1)
+            };
+            (lo, hi, count, bytes)
+        })
+        .collect();
+
+    leaks_by_bucket.sort_by(|a, b| b.0.cmp(&a.0)); // Sort by bucket, largest first
+    leaks_by_bucket
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats_with_sizes(sizes: &[usize]) -> MemoryStats {
+        let active_allocations = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &size)| {
+                (
+                    i,
+                    AllocationInfo {
+                        size,
+                        timestamp: chrono::Utc::now(),
+                        stack_trace: Vec::new(),
+                        thread_id: 0,
+                    },
+                )
+            })
+            .collect();
+
+        MemoryStats {
+            total_allocated: sizes.iter().sum(),
+            total_freed: 0,
+            current_usage: sizes.iter().sum(),
+            peak_usage: sizes.iter().sum(),
+            allocation_count: sizes.len() as u64,
+            free_count: 0,
+            active_allocations,
+        }
+    }
+
+    #[test]
+    fn bucket_leaks_log2_puts_a_power_of_two_size_in_its_own_closing_bucket() {
+        // 4096 must land in the bucket whose displayed range *includes* 4096, not the one
+        // ending just below it.
+        let stats = stats_with_sizes(&[4096]);
+        let buckets = bucket_leaks_log2(&stats);
+        assert_eq!(buckets.len(), 1);
+        let (lo, hi, count, bytes) = buckets[0];
+        assert!(lo <= 4096 && 4096 < hi, "range [{lo}, {hi}) must contain 4096");
+        assert_eq!(count, 1);
+        assert_eq!(bytes, 4096);
+    }
+
+    #[test]
+    fn bucket_leaks_log2_groups_zero_and_one_into_the_first_bucket() {
+        let stats = stats_with_sizes(&[0, 1]);
+        let buckets = bucket_leaks_log2(&stats);
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].2, 2);
+        assert_eq!(buckets[0].3, 1);
+    }
+
+    #[test]
+    fn bucket_leaks_log2_sorts_largest_bucket_first() {
+        let stats = stats_with_sizes(&[8, 1024, 2]);
+        let buckets = bucket_leaks_log2(&stats);
+        let ranges: Vec<(u64, u64)> = buckets.iter().map(|(lo, hi, _, _)| (*lo, *hi)).collect();
+        let mut sorted = ranges.clone();
+        sorted.sort_by(|a, b| b.0.cmp(&a.0));
+        assert_eq!(ranges, sorted);
+    }
+
+    #[test]
+    fn bucket_leaks_log2_every_bucket_range_contains_its_own_members() {
+        for size in [1usize, 2, 3, 4, 5, 4095, 4096, 4097, 1 << 20] {
+            let stats = stats_with_sizes(&[size]);
+            let (lo, hi, _, _) = bucket_leaks_log2(&stats)[0];
+            assert!(
+                (lo as usize) <= size && size < (hi as usize),
+                "size {size} not in displayed range [{lo}, {hi})"
+            );
+        }
     }
 }
\ No newline at end of file
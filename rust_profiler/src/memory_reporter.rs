@@ -0,0 +1,169 @@
+//! Pluggable memory reporters. `ProcessMonitor` only ever had one source of truth — the
+//! kernel's `/proc` view — so a process embedding an allocator with its own accounting (e.g.
+//! jemalloc) had no way to surface it. `MemoryReporter` lets additional sources register
+//! named, hierarchical measurements that get aggregated into one [`MemoryReport`] alongside
+//! the kernel's numbers, so memory can be attributed to logical components instead of one
+//! flat RSS figure.
+use std::collections::{BTreeMap, HashMap};
+
+/// Distinguishes a reporter's precise, non-overlapping-by-construction measurements from
+/// looser size estimates. Only `Explicit` parents are checked for the
+/// children-exceed-parent inconsistency in [`aggregate_reports`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    /// A measurement straight from the allocator/runtime's own accounting (e.g. jemalloc's
+    /// `stats.allocated`), trusted as an upper bound for anything nested under it.
+    Explicit,
+    /// A non-overlapping size estimate (e.g. RSS-derived) with no such accounting guarantee.
+    Estimate,
+}
+
+/// A source of named, `/`-separated memory measurements (e.g. `jemalloc/heap-allocated`,
+/// `system/rss`). Implementations are queried on demand by `ProcessMonitor::get_memory_report`.
+pub trait MemoryReporter: Send + Sync {
+    fn report(&self) -> Vec<(String, ReportKind, usize)>;
+}
+
+/// Wraps the kernel's `/proc`-derived RSS numbers as a `MemoryReporter`, so they aggregate
+/// alongside any other registered reporter instead of being a special case.
+pub struct ProcfsReporter {
+    pid: u32,
+}
+
+impl ProcfsReporter {
+    pub fn new(pid: u32) -> Self {
+        Self { pid }
+    }
+}
+
+impl MemoryReporter for ProcfsReporter {
+    fn report(&self) -> Vec<(String, ReportKind, usize)> {
+        // `/proc/<pid>/status`'s `VmRSS` and `/proc/<pid>/statm`'s resident page count are
+        // the same kernel quantity (the latter derived from the former via `page_size`), so
+        // only one is reported — emitting both under `system/*` would double the aggregated
+        // `system` total in `aggregate_reports`, which assumes sibling estimates are
+        // non-overlapping. `status` is preferred since it needs no page-size conversion;
+        // `statm` is the fallback for the (effectively theoretical) case `status` can't be
+        // read but `statm` can.
+        if let Ok(status) = std::fs::read(format!("/proc/{}/status", self.pid)) {
+            if let Some(vmrss_kb) = crate::process_monitor::parse_status_kv(&status, "VmRSS") {
+                return vec![("system/rss".to_string(), ReportKind::Estimate, (vmrss_kb * 1024) as usize)];
+            }
+        }
+
+        if let Ok(statm) = std::fs::read(format!("/proc/{}/statm", self.pid)) {
+            if let Some(resident_pages) = crate::process_monitor::parse_statm_resident_pages(&statm) {
+                let resident = (resident_pages * crate::process_monitor::page_size()) as usize;
+                return vec![("system/rss".to_string(), ReportKind::Estimate, resident)];
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+/// Reads jemalloc's own allocator statistics for the *current* process via `mallctl`. Only
+/// meaningful when the toolkit's own process (or a library embedding it) links jemalloc —
+/// `mallctl` has no cross-process equivalent, unlike the `/proc`-based reporters above.
+#[cfg(feature = "jemalloc-reporter")]
+pub struct JemallocReporter;
+
+#[cfg(feature = "jemalloc-reporter")]
+impl JemallocReporter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(feature = "jemalloc-reporter")]
+impl MemoryReporter for JemallocReporter {
+    fn report(&self) -> Vec<(String, ReportKind, usize)> {
+        use jemalloc_ctl::{epoch, stats};
+
+        if epoch::mib().and_then(|mib| mib.advance()).is_err() {
+            return Vec::new();
+        }
+
+        let mut entries = Vec::new();
+        if let Ok(allocated) = stats::allocated::read() {
+            entries.push(("jemalloc/heap-allocated".to_string(), ReportKind::Explicit, allocated));
+        }
+        if let Ok(resident) = stats::resident::read() {
+            entries.push(("jemalloc/resident".to_string(), ReportKind::Estimate, resident));
+        }
+
+        entries
+    }
+}
+
+/// Aggregated view of every registered reporter's measurements, keyed by path — including
+/// synthesized parent paths whose value is the sum of their children.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct MemoryReport {
+    pub totals: HashMap<String, usize>,
+    /// Raised when an `Explicit` parent's children sum to more than the parent's own
+    /// reported total — a sign two reporters double-counted, or a reporter double-reported
+    /// a subset of its own total under a child path.
+    pub inconsistencies: Vec<String>,
+}
+
+#[derive(Default)]
+struct PathNode {
+    own_value: Option<(ReportKind, usize)>,
+    children: BTreeMap<String, PathNode>,
+}
+
+/// Build a tree from every entry's `/`-separated path and sum children into parents,
+/// flagging any `Explicit` parent whose children sum to more than its own reported value.
+pub fn aggregate_reports(entries: Vec<(String, ReportKind, usize)>) -> MemoryReport {
+    let mut root = PathNode::default();
+    for (path, kind, bytes) in entries {
+        let mut node = &mut root;
+        for segment in path.split('/') {
+            node = node.children.entry(segment.to_string()).or_default();
+        }
+        node.own_value = Some((kind, bytes));
+    }
+
+    let mut totals = HashMap::new();
+    let mut inconsistencies = Vec::new();
+    aggregate_node("", &root, &mut totals, &mut inconsistencies);
+
+    MemoryReport { totals, inconsistencies }
+}
+
+fn aggregate_node(
+    prefix: &str,
+    node: &PathNode,
+    totals: &mut HashMap<String, usize>,
+    inconsistencies: &mut Vec<String>,
+) -> usize {
+    let mut children_sum = 0;
+    for (name, child) in &node.children {
+        let child_path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}/{name}")
+        };
+        children_sum += aggregate_node(&child_path, child, totals, inconsistencies);
+    }
+
+    let total = match node.own_value {
+        Some((ReportKind::Explicit, bytes)) => {
+            if children_sum > bytes {
+                inconsistencies.push(format!(
+                    "{prefix}: children sum to {children_sum} bytes but the explicit parent reported only {bytes}"
+                ));
+            }
+            bytes
+        }
+        Some((ReportKind::Estimate, bytes)) => bytes.max(children_sum),
+        None => children_sum,
+    };
+
+    if !prefix.is_empty() {
+        totals.insert(prefix.to_string(), total);
+    }
+
+    total
+}
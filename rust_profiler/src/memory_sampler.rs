@@ -0,0 +1,276 @@
+//! Background RSS sampler. `get_memory_stats` only captures a point-in-time snapshot driven
+//! by the profiler's own polling loop, so `peak_usage` is whatever `VmPeak` happened to be at
+//! the last tick and there's no distribution of memory occupancy over the run. `MemorySampler`
+//! polls independently on its own thread and folds every sample into a geometric-bucket
+//! histogram plus running min/max/mean, returned as a [`MemoryProfile`].
+use crate::process_monitor::ProcessMonitor;
+use crate::MemoryProfile;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Lower bound of the histogram's first bucket, in bytes. Samples below this value fall into
+/// bucket 0 rather than underflowing the logarithm.
+const MIN_BOUND_BYTES: f64 = 4096.0;
+/// Geometric growth factor between adjacent bucket boundaries.
+const BUCKET_BASE: f64 = 1.5;
+/// Bucket count sized so the histogram spans from `MIN_BOUND_BYTES` up to about 774 GB
+/// (`MIN_BOUND_BYTES * BUCKET_BASE^47`);
+/// anything larger lands in the dedicated overflow counter instead of growing the array.
+const BUCKET_COUNT: usize = 48;
+
+/// Map an RSS sample to a histogram bucket index, or `None` if it overflows the last bucket.
+fn bucket_index(value: usize) -> Option<usize> {
+    if (value as f64) < MIN_BOUND_BYTES {
+        return Some(0);
+    }
+
+    let raw = ((value as f64 / MIN_BOUND_BYTES).log(BUCKET_BASE)).floor() as i64 + 1;
+    if raw < 0 {
+        Some(0)
+    } else if (raw as usize) < BUCKET_COUNT {
+        Some(raw as usize)
+    } else {
+        None
+    }
+}
+
+/// Inclusive-low/exclusive-high byte range covered by `index`.
+fn bucket_bounds(index: usize) -> (usize, usize) {
+    if index == 0 {
+        return (0, MIN_BOUND_BYTES as usize);
+    }
+    let lo = (MIN_BOUND_BYTES * BUCKET_BASE.powi(index as i32 - 1)) as usize;
+    let hi = (MIN_BOUND_BYTES * BUCKET_BASE.powi(index as i32)) as usize;
+    (lo, hi)
+}
+
+struct Accumulator {
+    sample_count: u64,
+    min_rss: usize,
+    max_rss: usize,
+    sum_rss: u128,
+    self_observed_peak: usize,
+    histogram: [u64; BUCKET_COUNT],
+    overflow_count: u64,
+}
+
+impl Accumulator {
+    fn new() -> Self {
+        Self {
+            sample_count: 0,
+            min_rss: usize::MAX,
+            max_rss: 0,
+            sum_rss: 0,
+            self_observed_peak: 0,
+            histogram: [0; BUCKET_COUNT],
+            overflow_count: 0,
+        }
+    }
+
+    fn record(&mut self, rss: usize) {
+        self.sample_count += 1;
+        self.min_rss = self.min_rss.min(rss);
+        self.max_rss = self.max_rss.max(rss);
+        self.sum_rss += rss as u128;
+        self.self_observed_peak = self.self_observed_peak.max(rss);
+
+        match bucket_index(rss) {
+            Some(idx) => self.histogram[idx] += 1,
+            None => self.overflow_count += 1,
+        }
+    }
+
+    /// Fold the accumulated samples into a `MemoryProfile`. The self-observed peak can miss a
+    /// spike that happened between two polls, so `high_water_mark` (read independently from
+    /// `VmPeak`/`ru_maxrss`) is reconciled in by taking the larger of the two.
+    fn into_profile(self, high_water_mark: usize) -> MemoryProfile {
+        let mean_rss = if self.sample_count == 0 {
+            0.0
+        } else {
+            self.sum_rss as f64 / self.sample_count as f64
+        };
+
+        let histogram = self
+            .histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(idx, count)| {
+                let (lo, hi) = bucket_bounds(idx);
+                (lo, hi, *count)
+            })
+            .collect();
+
+        MemoryProfile {
+            sample_count: self.sample_count,
+            min_rss: if self.sample_count == 0 { 0 } else { self.min_rss },
+            max_rss: self.max_rss,
+            mean_rss,
+            high_water_mark: self.self_observed_peak.max(high_water_mark),
+            histogram,
+            overflow_count: self.overflow_count,
+        }
+    }
+}
+
+/// Handle to a background RSS-polling thread for a single process. `spawn` starts polling
+/// immediately; `finish` stops the thread and returns the accumulated profile.
+pub struct MemorySampler {
+    accumulator: Arc<Mutex<Accumulator>>,
+    stop_flag: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MemorySampler {
+    pub fn spawn(pid: u32, poll_interval: Duration) -> Self {
+        let accumulator = Arc::new(Mutex::new(Accumulator::new()));
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let worker_accumulator = accumulator.clone();
+        let worker_stop_flag = stop_flag.clone();
+
+        let handle = thread::spawn(move || {
+            let Ok(monitor) = ProcessMonitor::new(pid) else {
+                return;
+            };
+
+            while !worker_stop_flag.load(Ordering::Relaxed) {
+                if let Ok(rss) = monitor.sample_rss() {
+                    if let Ok(mut acc) = worker_accumulator.lock() {
+                        acc.record(rss);
+                    }
+                }
+
+                if !matches!(monitor.is_running(), Ok(true)) {
+                    break;
+                }
+
+                thread::sleep(poll_interval);
+            }
+        });
+
+        Self {
+            accumulator,
+            stop_flag,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stop the poll loop and fold the accumulated samples into a `MemoryProfile`, reconciled
+    /// against `high_water_mark`.
+    pub fn finish(mut self, high_water_mark: usize) -> MemoryProfile {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+
+        // `MemorySampler` implements `Drop`, so the field is swapped out rather than moved.
+        let taken = std::mem::replace(&mut self.accumulator, Arc::new(Mutex::new(Accumulator::new())));
+        let accumulator = match Arc::try_unwrap(taken) {
+            Ok(mutex) => mutex.into_inner().unwrap_or_else(|e| e.into_inner()),
+            Err(shared) => {
+                let guard = shared.lock().unwrap_or_else(|e| e.into_inner());
+                Accumulator {
+                    sample_count: guard.sample_count,
+                    min_rss: guard.min_rss,
+                    max_rss: guard.max_rss,
+                    sum_rss: guard.sum_rss,
+                    self_observed_peak: guard.self_observed_peak,
+                    histogram: guard.histogram,
+                    overflow_count: guard.overflow_count,
+                }
+            }
+        };
+
+        accumulator.into_profile(high_water_mark)
+    }
+}
+
+impl Drop for MemorySampler {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_index_puts_anything_below_the_minimum_in_bucket_zero() {
+        assert_eq!(bucket_index(0), Some(0));
+        assert_eq!(bucket_index(MIN_BOUND_BYTES as usize - 1), Some(0));
+    }
+
+    #[test]
+    fn bucket_index_grows_with_value_and_eventually_overflows() {
+        assert!(bucket_index(MIN_BOUND_BYTES as usize) > Some(0));
+        assert_eq!(bucket_index(usize::MAX), None);
+    }
+
+    #[test]
+    fn bucket_index_is_monotonically_nondecreasing() {
+        let mut prev = bucket_index(0).unwrap();
+        let mut value = MIN_BOUND_BYTES as usize;
+        for _ in 0..BUCKET_COUNT + 5 {
+            let idx = bucket_index(value);
+            if let Some(idx) = idx {
+                assert!(idx >= prev, "bucket index must not decrease as value grows");
+                prev = idx;
+            }
+            value = (value as f64 * BUCKET_BASE) as usize + 1;
+        }
+    }
+
+    #[test]
+    fn bucket_bounds_high_edge_maps_back_to_its_own_bucket() {
+        // `bucket_index` and `bucket_bounds` compute the boundary via `log`/`powi`
+        // respectively, so floating-point rounding can occasionally place the exact low
+        // edge one bucket earlier than `bucket_bounds` reports — but a value just below the
+        // high edge always round-trips, since that's unaffected by that rounding direction.
+        for idx in 0..BUCKET_COUNT {
+            let (lo, hi) = bucket_bounds(idx);
+            assert!(lo < hi, "bucket {idx} has an empty range [{lo}, {hi})");
+            assert_eq!(bucket_index(hi - 1), Some(idx));
+        }
+    }
+
+    #[test]
+    fn bucket_bounds_are_contiguous_across_adjacent_buckets() {
+        for idx in 0..BUCKET_COUNT - 1 {
+            let (_, hi) = bucket_bounds(idx);
+            let (next_lo, _) = bucket_bounds(idx + 1);
+            assert_eq!(hi, next_lo, "gap/overlap between bucket {idx} and {}", idx + 1);
+        }
+    }
+
+    #[test]
+    fn accumulator_records_min_max_mean_and_histogram() {
+        let mut acc = Accumulator::new();
+        for rss in [1024usize, 2048, 4096, 8192] {
+            acc.record(rss);
+        }
+
+        let profile = acc.into_profile(0);
+        assert_eq!(profile.sample_count, 4);
+        assert_eq!(profile.min_rss, 1024);
+        assert_eq!(profile.max_rss, 8192);
+        assert_eq!(profile.mean_rss, (1024 + 2048 + 4096 + 8192) as f64 / 4.0);
+        assert_eq!(profile.overflow_count, 0);
+        let total_in_histogram: u64 = profile.histogram.iter().map(|(_, _, count)| *count).sum();
+        assert_eq!(total_in_histogram, 4);
+    }
+
+    #[test]
+    fn accumulator_reconciles_high_water_mark_against_observed_peak() {
+        let mut acc = Accumulator::new();
+        acc.record(1024);
+        let profile = acc.into_profile(1 << 30);
+        assert_eq!(profile.high_water_mark, 1 << 30);
+    }
+}
@@ -1,9 +1,12 @@
-use crate::{AllocationInfo, MemoryStats};
+use crate::{AllocationInfo, MemoryStats, Snapshot};
 use std::collections::HashMap;
 
 pub struct MemoryTracker {
     current_stats: MemoryStats,
     peak_usage: usize,
+    history: Vec<Snapshot>,
+    current_cpu_percent: f64,
+    peak_cpu_percent: f64,
 }
 
 impl MemoryTracker {
@@ -19,10 +22,13 @@ impl MemoryTracker {
                 active_allocations: HashMap::new(),
             },
             peak_usage: 0,
+            history: Vec::new(),
+            current_cpu_percent: 0.0,
+            peak_cpu_percent: 0.0,
         }
     }
 
-    pub fn update_stats(&mut self, new_stats: MemoryStats) {
+    pub fn update_stats(&mut self, new_stats: MemoryStats, cpu_percent: f64) {
         // Track peak usage
         if new_stats.current_usage > self.peak_usage {
             self.peak_usage = new_stats.current_usage;
@@ -31,12 +37,46 @@ impl MemoryTracker {
         // Update current stats
         self.current_stats = new_stats;
         self.current_stats.peak_usage = self.peak_usage;
+
+        self.current_cpu_percent = cpu_percent;
+        if cpu_percent > self.peak_cpu_percent {
+            self.peak_cpu_percent = cpu_percent;
+        }
+
+        self.history.push(Snapshot {
+            timestamp: chrono::Utc::now(),
+            current_usage: self.current_stats.current_usage,
+            peak_usage: self.peak_usage,
+            total_allocated: self.current_stats.total_allocated,
+            allocation_count: self.current_stats.allocation_count,
+            active_allocations: self.current_stats.active_allocations.len(),
+            cpu_percent,
+        });
     }
 
     pub fn get_current_stats(&self) -> &MemoryStats {
         &self.current_stats
     }
 
+    pub fn get_current_cpu_percent(&self) -> f64 {
+        self.current_cpu_percent
+    }
+
+    pub fn get_peak_cpu_percent(&self) -> f64 {
+        self.peak_cpu_percent
+    }
+
+    pub fn get_average_cpu_percent(&self) -> f64 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+        self.history.iter().map(|s| s.cpu_percent).sum::<f64>() / self.history.len() as f64
+    }
+
+    pub fn get_history(&self) -> &[Snapshot] {
+        &self.history
+    }
+
     pub fn get_final_stats(self) -> MemoryStats {
         self.current_stats
     }
@@ -1,97 +1,239 @@
+use crate::memory_reporter::{aggregate_reports, MemoryReport, MemoryReporter, ProcfsReporter};
 use crate::MemoryStats;
-use anyhow::{Context, Result};
-use procfs::process::Process;
+use anyhow::{bail, Context, Result};
+use rustix::fd::OwnedFd;
+use rustix::fs::{open, Mode, OFlags};
+use rustix::io::pread;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
+/// `ProcessMonitor` used to go through the `procfs` crate, which allocates a fresh struct and
+/// several `String`s on every call — too costly once `get_memory_stats`/`get_memory_maps` are
+/// polled at high frequency (e.g. by `MemorySampler`). Instead, each `/proc/<pid>/*` file is
+/// opened once in `new()` and re-read with `pread` at offset 0 into a reused buffer, parsing
+/// only the handful of fields these methods actually need.
 pub struct ProcessMonitor {
     pid: u32,
-    process: Process,
+    stat_fd: OwnedFd,
+    statm_fd: OwnedFd,
+    status_fd: OwnedFd,
+    cmdline_fd: OwnedFd,
+    smaps_fd: OwnedFd,
+    buf: RefCell<Vec<u8>>,
+    maps_buf: RefCell<Vec<u8>>,
+    reporters: HashMap<String, Box<dyn MemoryReporter>>,
 }
 
 impl ProcessMonitor {
     pub fn new(pid: u32) -> Result<Self> {
-        let process = Process::new(pid as i32)
-            .context(format!("Failed to attach to process {}", pid))?;
+        let open_proc_file = |name: &str| -> Result<OwnedFd> {
+            open(format!("/proc/{pid}/{name}"), OFlags::RDONLY, Mode::empty())
+                .with_context(|| format!("Failed to open /proc/{pid}/{name}"))
+        };
+
+        let stat_fd = open_proc_file("stat")?;
+        let statm_fd = open_proc_file("statm")?;
+        let status_fd = open_proc_file("status")?;
+        let cmdline_fd = open_proc_file("cmdline")?;
+        let smaps_fd = open_proc_file("smaps")?;
+
+        let mut reporters: HashMap<String, Box<dyn MemoryReporter>> = HashMap::new();
+        reporters.insert("procfs".to_string(), Box::new(ProcfsReporter::new(pid)));
+        #[cfg(feature = "jemalloc-reporter")]
+        reporters.insert(
+            "jemalloc".to_string(),
+            Box::new(crate::memory_reporter::JemallocReporter::new()),
+        );
+        #[cfg(feature = "jemalloc-profiling")]
+        reporters.insert(
+            "jemalloc-profile".to_string(),
+            Box::new(crate::jemalloc_profiling::JemallocProfileReporter::new(format!(
+                "/tmp/valgrind-toolkit-heap-{pid}.prof"
+            ))),
+        );
+
+        Ok(Self {
+            pid,
+            stat_fd,
+            statm_fd,
+            status_fd,
+            cmdline_fd,
+            smaps_fd,
+            buf: RefCell::new(Vec::with_capacity(512)),
+            maps_buf: RefCell::new(Vec::with_capacity(8192)),
+            reporters,
+        })
+    }
+
+    /// Register an additional memory source; its measurements are merged into every
+    /// subsequent `get_memory_report()` call alongside the built-in `procfs` reporter.
+    pub fn register_reporter(&mut self, name: impl Into<String>, reporter: Box<dyn MemoryReporter>) {
+        self.reporters.insert(name.into(), reporter);
+    }
+
+    /// Query every registered reporter and aggregate their measurements into one
+    /// path-keyed `MemoryReport`, so memory can be attributed to logical components
+    /// (the kernel's `/proc` view, jemalloc's own accounting, etc.) rather than one flat RSS
+    /// number.
+    pub fn get_memory_report(&self) -> MemoryReport {
+        let entries = self.reporters.values().flat_map(|r| r.report()).collect();
+        aggregate_reports(entries)
+    }
 
-        Ok(Self { pid, process })
+    /// Toggle jemalloc's heap profiler (`prof.active`) for the toolkit's own process — see
+    /// `jemalloc_profiling`'s module docs for why this targets the toolkit's own allocator.
+    /// Must be active for a subsequent `dump_jemalloc_heap_profile` call or the
+    /// `jemalloc-profile` entry in `get_memory_report()` to contain any records.
+    #[cfg(feature = "jemalloc-profiling")]
+    pub fn set_jemalloc_profiling_active(&self, active: bool) -> Result<()> {
+        crate::jemalloc_profiling::set_profiling_active(active)
+    }
+
+    /// Trigger a jemalloc heap-profile dump to `path` and fold the per-call-site records into
+    /// a `MemoryStats`, the same shape `get_memory_stats` returns, so a jemalloc-sourced
+    /// breakdown can be consumed like any other source of truth this type already models.
+    #[cfg(feature = "jemalloc-profiling")]
+    pub fn dump_jemalloc_heap_profile(&self, path: &str) -> Result<MemoryStats> {
+        let records = crate::jemalloc_profiling::dump_heap_profile(path)?;
+        Ok(crate::jemalloc_profiling::records_to_memory_stats(&records))
     }
 
     pub async fn get_memory_stats(&self) -> Result<MemoryStats> {
-        let stat = self.process.stat().context("Failed to read process stat")?;
-        let statm = self.process.statm().context("Failed to read process statm")?;
-        let status = self.process.status().context("Failed to read process status")?;
-
-        // Calculate memory usage from /proc/pid/statm
-        let page_size = procfs::page_size();
-        let current_usage = (statm.resident * page_size) as usize;
-
-        // Try to get more detailed memory info from /proc/pid/status
-        let vmrss = status
-            .vmrss
-            .map(|kb| kb * 1024)
-            .unwrap_or(current_usage);
-        let vmpeak = status
-            .vmpeak
-            .map(|kb| kb * 1024)
-            .unwrap_or(current_usage);
+        let mut buf = self.buf.borrow_mut();
+
+        if !read_whole_file(&self.statm_fd, &mut buf) {
+            bail!("Failed to read process statm");
+        }
+        let resident_pages = parse_statm_resident_pages(&buf).context("Failed to parse process statm")?;
+        let current_usage = resident_pages * page_size();
+
+        if !read_whole_file(&self.status_fd, &mut buf) {
+            bail!("Failed to read process status");
+        }
+        let vmrss = parse_status_kv(&buf, "VmRSS").map(|kb| kb * 1024).unwrap_or(current_usage);
+        let vmpeak = parse_status_kv(&buf, "VmPeak").map(|kb| kb * 1024).unwrap_or(current_usage);
 
         // For this simplified version, we'll use the RSS as current usage
         // In a real implementation, you'd need to hook into malloc/free or use ptrace
-        let stats = MemoryStats {
-            total_allocated: vmpeak,
-            total_freed: vmpeak.saturating_sub(vmrss),
-            current_usage: vmrss,
-            peak_usage: vmpeak,
+        Ok(MemoryStats {
+            total_allocated: vmpeak as usize,
+            total_freed: vmpeak.saturating_sub(vmrss) as usize,
+            current_usage: vmrss as usize,
+            peak_usage: vmpeak as usize,
             allocation_count: 0, // Would need to track this separately
             free_count: 0,       // Would need to track this separately
             active_allocations: HashMap::new(), // Would need malloc/free hooking
+        })
+    }
+
+    /// Cumulative CPU time consumed by the process, in clock ticks (`utime + stime` from
+    /// `/proc/<pid>/stat`). Combine with `cpu_clock_ticks_per_sec()` and a wall-clock delta
+    /// between two calls to derive a CPU utilization percentage.
+    pub fn get_cpu_jiffies(&self) -> Result<u64> {
+        let mut buf = self.buf.borrow_mut();
+        if !read_whole_file(&self.stat_fd, &mut buf) {
+            bail!("Failed to read process stat");
+        }
+        parse_stat_jiffies(&buf).context("Failed to parse process stat")
+    }
+
+    /// The kernel's clock ticks per second (`sysconf(_SC_CLK_TCK)`), needed to convert
+    /// `get_cpu_jiffies` deltas into seconds.
+    pub fn cpu_clock_ticks_per_sec() -> u64 {
+        unsafe { libc::sysconf(libc::_SC_CLK_TCK) as u64 }
+    }
+
+    /// Current RSS in bytes (`statm.resident * page_size`), polled by `MemorySampler` for its
+    /// exponential-bucket histogram.
+    pub fn sample_rss(&self) -> Result<usize> {
+        let mut buf = self.buf.borrow_mut();
+        if !read_whole_file(&self.statm_fd, &mut buf) {
+            bail!("Failed to read process statm");
+        }
+        let resident_pages = parse_statm_resident_pages(&buf).context("Failed to parse process statm")?;
+        Ok((resident_pages * page_size()) as usize)
+    }
+
+    /// The kernel's high-water mark for this process, reconciled from `/proc/<pid>/status`'s
+    /// `VmPeak` and `getrusage(RUSAGE_CHILDREN)`'s `ru_maxrss` (only populated once a spawned
+    /// child has exited, and only for children of this process), taking the larger of the
+    /// two. `MemorySampler` folds this in against its own observed peak, which can miss a
+    /// short-lived spike that happened between two polls.
+    pub fn high_water_mark(&self) -> Result<usize> {
+        let mut buf = self.buf.borrow_mut();
+        if !read_whole_file(&self.status_fd, &mut buf) {
+            bail!("Failed to read process status");
+        }
+        let vmpeak = parse_status_kv(&buf, "VmPeak").map(|kb| (kb * 1024) as usize).unwrap_or(0);
+        drop(buf);
+
+        let ru_maxrss = unsafe {
+            let mut usage: libc::rusage = std::mem::zeroed();
+            if libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) == 0 {
+                (usage.ru_maxrss.max(0) as usize) * 1024
+            } else {
+                0
+            }
         };
 
-        Ok(stats)
+        Ok(vmpeak.max(ru_maxrss))
     }
 
-    pub fn is_running(&self) -> Result<bool> {
-        match self.process.stat() {
-            Ok(_) => Ok(true),
-            Err(_) => Ok(false),
+    /// The process's current scheduling state, parsed from `/proc/<pid>/stat`'s single-
+    /// character state field. Lets callers distinguish a live process from a zombie, a
+    /// stopped/traced process, or uninterruptible sleep — all of which matter when driving
+    /// a ptrace-based analysis session.
+    pub fn get_process_state(&self) -> Result<ProcessState> {
+        let mut buf = self.buf.borrow_mut();
+        if !read_whole_file(&self.stat_fd, &mut buf) || buf.is_empty() {
+            return Ok(ProcessState::Dead);
         }
+        Ok(parse_stat_state(&buf).unwrap_or(ProcessState::Dead))
+    }
+
+    pub fn is_running(&self) -> Result<bool> {
+        Ok(!matches!(
+            self.get_process_state()?,
+            ProcessState::Dead | ProcessState::Zombie
+        ))
     }
 
     pub fn get_command_line(&self) -> Result<String> {
-        let cmdline = self.process.cmdline().context("Failed to read cmdline")?;
-        Ok(cmdline.join(" "))
+        let mut buf = self.buf.borrow_mut();
+        if !read_whole_file(&self.cmdline_fd, &mut buf) {
+            bail!("Failed to read cmdline");
+        }
+
+        let text = String::from_utf8_lossy(&buf);
+        let parts: Vec<&str> = text.split('\0').filter(|s| !s.is_empty()).collect();
+        Ok(parts.join(" "))
     }
 
     pub fn get_pid(&self) -> u32 {
         self.pid
     }
 
+    /// Per-mapping memory breakdown. `/proc/<pid>/maps` alone only gives address ranges and
+    /// permissions, not actual consumption, so this reads `/proc/<pid>/smaps` instead, which
+    /// repeats every mapping header followed by its `Rss`/`Pss`/`Private_Dirty`/`Shared_Clean`
+    /// fields.
     pub async fn get_memory_maps(&self) -> Result<Vec<MemoryMapping>> {
-        let maps = self.process.maps().context("Failed to read memory maps")?;
-        let mut mappings = Vec::new();
-
-        for map in maps {
-            mappings.push(MemoryMapping {
-                start_address: map.address.0,
-                end_address: map.address.1,
-                size: map.address.1 - map.address.0,
-                permissions: format!("{:?}", map.perms),
-                pathname: map.pathname.map(|p| format!("{:?}", p)),
-            });
+        let mut buf = self.maps_buf.borrow_mut();
+        if !read_whole_file(&self.smaps_fd, &mut buf) {
+            bail!("Failed to read /proc/<pid>/smaps");
         }
-
-        Ok(mappings)
+        let contents = String::from_utf8_lossy(&buf);
+        Ok(parse_smaps(&contents))
     }
 
     pub async fn get_open_files(&self) -> Result<Vec<String>> {
-        let fd_dir = self.process.fd().context("Failed to read file descriptors")?;
+        let dir = std::fs::read_dir(format!("/proc/{}/fd", self.pid)).context("Failed to read file descriptors")?;
         let mut files = Vec::new();
 
-        for fd_entry in fd_dir {
-            if let Ok(fd) = fd_entry {
-                if let Ok(target) = fd.target() {
-                    files.push(format!("{}: {:?}", fd.fd, target));
-                }
+        for entry in dir.flatten() {
+            let fd_name = entry.file_name().to_string_lossy().into_owned();
+            if let Ok(target) = std::fs::read_link(entry.path()) {
+                files.push(format!("{}: {}", fd_name, target.display()));
             }
         }
 
@@ -99,6 +241,127 @@ impl ProcessMonitor {
     }
 }
 
+/// The kernel's page size in bytes (`sysconf(_SC_PAGESIZE)`), needed to convert `statm`'s
+/// page-count fields into bytes.
+pub(crate) fn page_size() -> u64 {
+    rustix::param::page_size() as u64
+}
+
+/// Read the entirety of a `/proc` file into `buf`, reusing its existing allocation instead of
+/// allocating a fresh `String`/`Vec` each call. `/proc` files don't support `lseek`-based
+/// appends meaningfully, so this always re-reads from offset 0 via `pread`, growing `buf` in
+/// fixed chunks until a zero-length read signals EOF. Returns `false` (never panics) on any
+/// read error, e.g. a race where the target exited mid-read.
+fn read_whole_file(fd: &OwnedFd, buf: &mut Vec<u8>) -> bool {
+    buf.clear();
+    let mut offset: u64 = 0;
+
+    loop {
+        let start = buf.len();
+        buf.resize(start + 4096, 0);
+
+        match pread(fd, &mut buf[start..], offset) {
+            Ok(0) => {
+                buf.truncate(start);
+                return true;
+            }
+            Ok(n) => {
+                buf.truncate(start + n);
+                offset += n as u64;
+            }
+            Err(_) => {
+                buf.truncate(start);
+                return false;
+            }
+        }
+    }
+}
+
+/// Parse `/proc/<pid>/stat`'s `utime`/`stime` fields (12th/13th after the closing `)` of
+/// `comm`). `comm` itself can contain spaces or parentheses, so this scans for the *last*
+/// `)` rather than naively splitting on whitespace from the start of the line.
+fn parse_stat_jiffies(buf: &[u8]) -> Option<u64> {
+    let text = std::str::from_utf8(buf).ok()?;
+    let close_paren = text.rfind(')')?;
+    let mut fields = text.get(close_paren + 1..)?.split_whitespace();
+
+    let _state = fields.next()?;
+    let _ppid = fields.next()?;
+    let _pgrp = fields.next()?;
+    let _session = fields.next()?;
+    let _tty_nr = fields.next()?;
+    let _tpgid = fields.next()?;
+    let _flags = fields.next()?;
+    let _minflt = fields.next()?;
+    let _cminflt = fields.next()?;
+    let _majflt = fields.next()?;
+    let _cmajflt = fields.next()?;
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+
+    Some(utime + stime)
+}
+
+/// Parse `/proc/<pid>/stat`'s single-character state field, which immediately follows the
+/// closing `)` of the (possibly space-containing) `comm` field.
+fn parse_stat_state(buf: &[u8]) -> Option<ProcessState> {
+    let text = std::str::from_utf8(buf).ok()?;
+    let close_paren = text.rfind(')')?;
+    let state_char = text.get(close_paren + 1..)?.split_whitespace().next()?.chars().next()?;
+    Some(ProcessState::from_char(state_char))
+}
+
+/// Parse `/proc/<pid>/statm`'s second field (resident set size, in pages).
+pub(crate) fn parse_statm_resident_pages(buf: &[u8]) -> Option<u64> {
+    let text = std::str::from_utf8(buf).ok()?;
+    let mut fields = text.split_whitespace();
+    let _size = fields.next()?;
+    fields.next()?.parse().ok()
+}
+
+/// Scan `/proc/<pid>/status`'s `Key:\tvalue kB` lines for `key`'s value, without building a
+/// `HashMap` of every field.
+pub(crate) fn parse_status_kv(buf: &[u8], key: &str) -> Option<u64> {
+    let text = String::from_utf8_lossy(buf);
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix(key).and_then(|rest| rest.strip_prefix(':')) {
+            return rest.trim().split_whitespace().next()?.parse().ok();
+        }
+    }
+    None
+}
+
+/// A process's scheduling state, as reported by the kernel in `/proc/<pid>/stat`'s single
+/// state character. `Unknown` carries the unrecognized character so callers can still log it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    Sleeping,
+    UninterruptibleSleep,
+    Stopped,
+    Tracing,
+    Zombie,
+    Dead,
+    Idle,
+    Unknown(char),
+}
+
+impl ProcessState {
+    fn from_char(c: char) -> Self {
+        match c {
+            'R' => Self::Running,
+            'S' => Self::Sleeping,
+            'D' => Self::UninterruptibleSleep,
+            'T' => Self::Stopped,
+            't' => Self::Tracing,
+            'Z' => Self::Zombie,
+            'X' | 'x' => Self::Dead,
+            'I' => Self::Idle,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MemoryMapping {
     pub start_address: u64,
@@ -106,4 +369,280 @@ pub struct MemoryMapping {
     pub size: u64,
     pub permissions: String,
     pub pathname: Option<String>,
-}
\ No newline at end of file
+    pub rss: usize,
+    pub pss: usize,
+    pub private_dirty: usize,
+    pub shared_clean: usize,
+    pub region_kind: RegionKind,
+}
+
+/// Coarse classification of a mapping's pathname, so callers can see at a glance which
+/// libraries and which anonymous regions dominate a process's footprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegionKind {
+    Heap,
+    Stack,
+    Anonymous,
+    SharedLibrary,
+    File,
+}
+
+impl RegionKind {
+    fn classify(pathname: Option<&str>) -> Self {
+        match pathname {
+            Some("[heap]") => Self::Heap,
+            Some(p) if p.starts_with("[stack") => Self::Stack,
+            Some(p) if p.is_empty() || p.starts_with('[') => Self::Anonymous,
+            Some(p) if p.contains(".so") => Self::SharedLibrary,
+            Some(_) => Self::File,
+            None => Self::Anonymous,
+        }
+    }
+}
+
+/// Parse `/proc/<pid>/smaps`: each mapping's header line (same format as `/proc/<pid>/maps`)
+/// is followed by its `Size:`/`Rss:`/`Pss:`/`Private_Dirty:`/`Shared_Clean:` key/value lines
+/// until the next header.
+fn parse_smaps(contents: &str) -> Vec<MemoryMapping> {
+    let mut mappings = Vec::new();
+    let mut current: Option<MemoryMapping> = None;
+
+    for line in contents.lines() {
+        if is_field_line(line) {
+            if let Some(mapping) = current.as_mut() {
+                apply_field_line(mapping, line);
+            }
+        } else if let Some(mapping) = parse_header_line(line) {
+            if let Some(finished) = current.replace(mapping) {
+                mappings.push(finished);
+            }
+        }
+    }
+
+    if let Some(mapping) = current {
+        mappings.push(mapping);
+    }
+
+    mappings
+}
+
+/// Field lines look like `Rss:          1234 kB`; header lines never contain a `:` after
+/// a leading word made only of ASCII letters, digits and `_`, so that's enough to tell them
+/// apart.
+fn is_field_line(line: &str) -> bool {
+    match line.split_once(':') {
+        Some((key, _)) => !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        None => false,
+    }
+}
+
+fn parse_header_line(line: &str) -> Option<MemoryMapping> {
+    let mut parts = line.split_whitespace();
+    let range = parts.next()?;
+    let permissions = parts.next()?.to_string();
+    let _offset = parts.next()?;
+    let _dev = parts.next()?;
+    let _inode = parts.next()?;
+    let pathname = parts.next().map(|s| s.to_string());
+
+    let (start_str, end_str) = range.split_once('-')?;
+    let start_address = u64::from_str_radix(start_str, 16).ok()?;
+    let end_address = u64::from_str_radix(end_str, 16).ok()?;
+    let region_kind = RegionKind::classify(pathname.as_deref());
+
+    Some(MemoryMapping {
+        start_address,
+        end_address,
+        size: end_address.saturating_sub(start_address),
+        permissions,
+        pathname,
+        rss: 0,
+        pss: 0,
+        private_dirty: 0,
+        shared_clean: 0,
+        region_kind,
+    })
+}
+
+fn apply_field_line(mapping: &mut MemoryMapping, line: &str) {
+    let Some((key, rest)) = line.split_once(':') else {
+        return;
+    };
+    let Some(value_kb) = rest.trim().split_whitespace().next().and_then(|v| v.parse::<usize>().ok()) else {
+        return;
+    };
+    let bytes = value_kb * 1024;
+
+    match key {
+        "Rss" => mapping.rss = bytes,
+        "Pss" => mapping.pss = bytes,
+        "Private_Dirty" => mapping.private_dirty = bytes,
+        "Shared_Clean" => mapping.shared_clean = bytes,
+        _ => {}
+    }
+}
+
+/// Sum PSS (proportional set size) by region kind, so total private vs. shared footprint is
+/// reported without double-counting pages shared across mappings, e.g. a shared library's
+/// text segment mapped into many processes.
+pub fn aggregate_pss_by_region_kind(mappings: &[MemoryMapping]) -> HashMap<RegionKind, usize> {
+    let mut totals: HashMap<RegionKind, usize> = HashMap::new();
+    for mapping in mappings {
+        *totals.entry(mapping.region_kind).or_insert(0) += mapping.pss;
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMAPS_SAMPLE: &str = "\
+00400000-00452000 r-xp 00000000 08:02 173521 /usr/bin/dbus-daemon
+Size:                328 kB
+Rss:                 224 kB
+Pss:                 100 kB
+Shared_Clean:        224 kB
+Private_Dirty:         0 kB
+7f4a2b400000-7f4a2b600000 rw-p 00000000 00:00 0 [heap]
+Size:                2048 kB
+Rss:                1024 kB
+Pss:                1024 kB
+Shared_Clean:          0 kB
+Private_Dirty:      1024 kB
+7ffe12340000-7ffe12361000 rw-p 00000000 00:00 0 [stack]
+Size:                132 kB
+Rss:                  8 kB
+Pss:                   8 kB
+Shared_Clean:          0 kB
+Private_Dirty:         8 kB
+";
+
+    #[test]
+    fn parse_smaps_splits_one_mapping_per_header() {
+        let mappings = parse_smaps(SMAPS_SAMPLE);
+        assert_eq!(mappings.len(), 3);
+    }
+
+    #[test]
+    fn parse_smaps_reads_rss_pss_and_dirty_fields() {
+        let mappings = parse_smaps(SMAPS_SAMPLE);
+        let heap = &mappings[1];
+        assert_eq!(heap.rss, 1024 * 1024);
+        assert_eq!(heap.pss, 1024 * 1024);
+        assert_eq!(heap.private_dirty, 1024 * 1024);
+        assert_eq!(heap.shared_clean, 0);
+    }
+
+    #[test]
+    fn parse_smaps_classifies_region_kind_from_pathname() {
+        let mappings = parse_smaps(SMAPS_SAMPLE);
+        assert_eq!(mappings[0].region_kind, RegionKind::File);
+        assert_eq!(mappings[1].region_kind, RegionKind::Heap);
+        assert_eq!(mappings[2].region_kind, RegionKind::Stack);
+    }
+
+    #[test]
+    fn parse_smaps_computes_size_from_header_range() {
+        let mappings = parse_smaps(SMAPS_SAMPLE);
+        assert_eq!(mappings[0].start_address, 0x00400000);
+        assert_eq!(mappings[0].end_address, 0x00452000);
+        assert_eq!(mappings[0].size, 0x00452000 - 0x00400000);
+    }
+
+    #[test]
+    fn aggregate_pss_by_region_kind_sums_across_mappings() {
+        let mappings = parse_smaps(SMAPS_SAMPLE);
+        let totals = aggregate_pss_by_region_kind(&mappings);
+        assert_eq!(totals.get(&RegionKind::Heap), Some(&(1024 * 1024)));
+        assert_eq!(totals.get(&RegionKind::Stack), Some(&(8 * 1024)));
+    }
+
+    #[test]
+    fn parse_smaps_ignores_trailing_blank_input() {
+        assert!(parse_smaps("").is_empty());
+    }
+
+    #[test]
+    fn parse_stat_jiffies_sums_utime_and_stime() {
+        // utime=100 (14th field), stime=50 (15th field); the 12th/13th after `comm`'s `)`.
+        let stat = b"1234 (my proc) S 1 1234 1234 0 -1 4194304 100 0 0 0 100 50 0 0 20 0 1 0 0 0\n";
+        assert_eq!(parse_stat_jiffies(stat), Some(150));
+    }
+
+    #[test]
+    fn parse_stat_jiffies_handles_comm_containing_spaces_and_parens() {
+        // `comm` contains both spaces and nested parentheses — only the *last* `)` in the
+        // line marks the end of the comm field.
+        let stat = b"42 (weird (proc) name) R 1 42 42 0 -1 4194304 0 0 0 0 7 3 0 0 20 0 1 0 0 0\n";
+        assert_eq!(parse_stat_jiffies(stat), Some(10));
+    }
+
+    #[test]
+    fn parse_stat_jiffies_returns_none_on_truncated_read() {
+        let stat = b"1234 (proc) S 1 1234";
+        assert_eq!(parse_stat_jiffies(stat), None);
+    }
+
+    #[test]
+    fn parse_statm_resident_pages_reads_second_field() {
+        let statm = b"4096 512 256 10 0 400 0\n";
+        assert_eq!(parse_statm_resident_pages(statm), Some(512));
+    }
+
+    #[test]
+    fn parse_statm_resident_pages_returns_none_on_empty_input() {
+        assert_eq!(parse_statm_resident_pages(b""), None);
+    }
+
+    const STATUS_SAMPLE: &str = "\
+Name:\tmy proc
+State:\tS (sleeping)
+VmPeak:\t   12345 kB
+VmRSS:\t    6789 kB
+Threads:\t4
+";
+
+    #[test]
+    fn parse_status_kv_finds_exact_key_match() {
+        assert_eq!(parse_status_kv(STATUS_SAMPLE.as_bytes(), "VmRSS"), Some(6789));
+        assert_eq!(parse_status_kv(STATUS_SAMPLE.as_bytes(), "VmPeak"), Some(12345));
+    }
+
+    #[test]
+    fn parse_status_kv_does_not_match_a_key_that_is_only_a_prefix() {
+        // `Vm` is a prefix of `VmRSS`/`VmPeak` but must not itself match either line.
+        assert_eq!(parse_status_kv(STATUS_SAMPLE.as_bytes(), "Vm"), None);
+    }
+
+    #[test]
+    fn parse_status_kv_returns_none_for_missing_key() {
+        assert_eq!(parse_status_kv(STATUS_SAMPLE.as_bytes(), "VmSwap"), None);
+    }
+
+    #[test]
+    fn parse_stat_state_reads_the_character_after_comm() {
+        let stat = b"1234 (my proc) S 1 1234 1234 0 -1 4194304 0 0 0 0 0 0 0 0 20 0 1 0 0 0\n";
+        assert_eq!(parse_stat_state(stat), Some(ProcessState::Sleeping));
+    }
+
+    #[test]
+    fn parse_stat_state_handles_comm_containing_parens() {
+        let stat = b"42 (weird (proc) name) Z 1 42 42 0 -1 4194304 0 0 0 0 0 0 0 0 20 0 1 0 0 0\n";
+        assert_eq!(parse_stat_state(stat), Some(ProcessState::Zombie));
+    }
+
+    #[test]
+    fn process_state_from_char_maps_every_documented_code() {
+        assert_eq!(ProcessState::from_char('R'), ProcessState::Running);
+        assert_eq!(ProcessState::from_char('S'), ProcessState::Sleeping);
+        assert_eq!(ProcessState::from_char('D'), ProcessState::UninterruptibleSleep);
+        assert_eq!(ProcessState::from_char('T'), ProcessState::Stopped);
+        assert_eq!(ProcessState::from_char('t'), ProcessState::Tracing);
+        assert_eq!(ProcessState::from_char('Z'), ProcessState::Zombie);
+        assert_eq!(ProcessState::from_char('X'), ProcessState::Dead);
+        assert_eq!(ProcessState::from_char('x'), ProcessState::Dead);
+        assert_eq!(ProcessState::from_char('I'), ProcessState::Idle);
+        assert_eq!(ProcessState::from_char('?'), ProcessState::Unknown('?'));
+    }
+}
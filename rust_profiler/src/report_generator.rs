@@ -1,7 +1,163 @@
 use crate::ProfileReport;
 use prettytable::{Cell, Row, Table};
+use std::collections::BTreeMap;
 use std::fmt::Write;
 
+/// A single frame in the folded call tree built from `AllocationInfo::stack_trace`.
+///
+/// Every node accumulates the total bytes and allocation count of every active allocation
+/// whose trace passes through that frame, so the tree can be rendered top-down sorted by
+/// bytes descending.
+#[derive(Debug, Default)]
+pub struct StackTreeNode {
+    pub total_bytes: usize,
+    pub allocation_count: usize,
+    pub children: BTreeMap<String, StackTreeNode>,
+}
+
+/// Collapse-threshold filter for the stack tree: nodes deeper than `max_depth` or holding
+/// fewer than `longer_than` total bytes are folded into a `"(N frames, M bytes)"` summary
+/// child, borrowed from the rust-analyzer profiler's filtering idea.
+#[derive(Debug, Clone, Copy)]
+pub struct StackTreeFilter {
+    pub max_depth: Option<usize>,
+    pub longer_than: usize,
+}
+
+impl StackTreeFilter {
+    /// Parse a filter spec like `depth@8,longer_than=4096`. Unknown keys are ignored.
+    pub fn parse(spec: &str) -> Self {
+        let mut filter = Self {
+            max_depth: None,
+            longer_than: 0,
+        };
+
+        for part in spec.split(',') {
+            let part = part.trim();
+            if let Some(depth) = part.strip_prefix("depth@") {
+                filter.max_depth = depth.trim().parse().ok();
+            } else if let Some(bytes) = part.strip_prefix("longer_than=") {
+                filter.longer_than = bytes.trim().parse().unwrap_or(0);
+            }
+        }
+
+        filter
+    }
+}
+
+/// Fold every active allocation's `stack_trace` into a call-tree keyed by frame, inserting
+/// frames top-down so every node on the path accumulates the leak's size and count.
+pub fn build_stack_tree(report: &ProfileReport, filter: StackTreeFilter) -> StackTreeNode {
+    let mut root = StackTreeNode::default();
+
+    for info in report.memory_stats.active_allocations.values() {
+        let mut node = &mut root;
+        for frame in &info.stack_trace {
+            node.total_bytes += info.size;
+            node.allocation_count += 1;
+            node = node.children.entry(frame.clone()).or_default();
+        }
+        node.total_bytes += info.size;
+        node.allocation_count += 1;
+    }
+
+    collapse(&mut root, &filter, 0);
+    root
+}
+
+fn collapse(node: &mut StackTreeNode, filter: &StackTreeFilter, depth: usize) {
+    let over_depth = filter.max_depth.map_or(false, |max| depth >= max);
+
+    if over_depth && !node.children.is_empty() {
+        let frames = count_descendant_frames(node);
+        let bytes = node.children.values().map(|c| c.total_bytes).sum();
+        node.children.clear();
+        node.children.insert(
+            format!("({frames} frames, {bytes} bytes)"),
+            StackTreeNode {
+                total_bytes: bytes,
+                allocation_count: 0,
+                children: BTreeMap::new(),
+            },
+        );
+        return;
+    }
+
+    let small: Vec<String> = node
+        .children
+        .iter()
+        .filter(|(_, child)| child.total_bytes < filter.longer_than)
+        .map(|(frame, _)| frame.clone())
+        .collect();
+    if !small.is_empty() {
+        let (mut frames, mut bytes) = (0, 0);
+        for frame in small {
+            let child = node.children.remove(&frame).unwrap();
+            frames += 1 + count_descendant_frames(&child);
+            bytes += child.total_bytes;
+        }
+        let summary = StackTreeNode { total_bytes: bytes, ..Default::default() };
+        node.children.insert(format!("({frames} frames, {bytes} bytes)"), summary);
+    }
+    for child in node.children.values_mut() {
+        collapse(child, filter, depth + 1);
+    }
+}
+
+fn count_descendant_frames(node: &StackTreeNode) -> usize {
+    node.children
+        .values()
+        .map(|c| 1 + count_descendant_frames(c))
+        .sum()
+}
+
+/// Render the tree as an indented list, each level sorted by bytes descending.
+pub fn render_stack_tree(root: &StackTreeNode) -> String {
+    let mut output = String::new();
+    let mut ordered: Vec<_> = root.children.iter().collect();
+    ordered.sort_by(|a, b| b.1.total_bytes.cmp(&a.1.total_bytes));
+    for (frame, child) in ordered {
+        render_node(&mut output, frame, child, 0);
+    }
+    output
+}
+
+fn render_node(output: &mut String, frame: &str, node: &StackTreeNode, depth: usize) {
+    writeln!(
+        output,
+        "{}{} - {} ({} allocations)",
+        "  ".repeat(depth),
+        frame,
+        ReportGenerator::format_bytes(node.total_bytes),
+        node.allocation_count,
+    )
+    .unwrap();
+
+    let mut ordered: Vec<_> = node.children.iter().collect();
+    ordered.sort_by(|a, b| b.1.total_bytes.cmp(&a.1.total_bytes));
+    for (child_frame, child) in ordered {
+        render_node(output, child_frame, child, depth + 1);
+    }
+}
+
+/// Emit `frame1;frame2;frame3 total_bytes` lines for every root-to-leaf path, in the format
+/// an external flamegraph tool expects.
+pub fn generate_folded_stacks(report: &ProfileReport) -> String {
+    let mut totals: BTreeMap<String, usize> = BTreeMap::new();
+    for info in report.memory_stats.active_allocations.values() {
+        if info.stack_trace.is_empty() {
+            continue;
+        }
+        *totals.entry(info.stack_trace.join(";")).or_insert(0) += info.size;
+    }
+
+    let mut output = String::new();
+    for (path, bytes) in totals {
+        writeln!(output, "{} {}", path, bytes).unwrap();
+    }
+    output
+}
+
 pub struct ReportGenerator;
 
 impl ReportGenerator {
@@ -19,6 +175,188 @@ impl ReportGenerator {
         self.print_memory_statistics(report);
         self.print_leak_analysis(report);
         self.print_allocation_details(report);
+        self.print_stack_tree(report);
+        self.print_timeline(report);
+        self.print_memory_profile(report);
+        self.print_memory_report(report);
+        self.print_cgroup_stats(report);
+    }
+
+    /// Render the aggregated, path-keyed view from `ProcessMonitor::get_memory_report`, so
+    /// every registered `MemoryReporter` (the kernel's own `/proc` numbers, plus any others
+    /// registered alongside it) is attributed by source instead of collapsed into one RSS
+    /// figure.
+    fn print_memory_report(&self, report: &ProfileReport) {
+        let memory_report = &report.memory_report;
+        if memory_report.totals.is_empty() {
+            return;
+        }
+
+        println!("=== MEMORY REPORT (by source) ===");
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![Cell::new("Path"), Cell::new("Bytes")]));
+        for (path, bytes) in &memory_report.totals {
+            table.add_row(Row::new(vec![Cell::new(path), Cell::new(&Self::format_bytes(*bytes))]));
+        }
+        table.printstd();
+
+        for inconsistency in &memory_report.inconsistencies {
+            println!("Warning: {}", inconsistency);
+        }
+
+        println!();
+    }
+
+    /// Render the RSS distribution collected by `MemorySampler` over the process's lifetime.
+    fn print_memory_profile(&self, report: &ProfileReport) {
+        let profile = &report.memory_profile;
+        if profile.sample_count == 0 {
+            return;
+        }
+
+        println!("=== MEMORY PROFILE (RSS) ===");
+        println!("Samples: {}", profile.sample_count);
+        println!("Min: {}", Self::format_bytes(profile.min_rss));
+        println!("Mean: {}", Self::format_bytes(profile.mean_rss as usize));
+        println!("Max: {}", Self::format_bytes(profile.max_rss));
+        println!("High-water mark: {}", Self::format_bytes(profile.high_water_mark));
+
+        if !profile.histogram.is_empty() {
+            let max_count = profile.histogram.iter().map(|(_, _, c)| *c).max().unwrap_or(1).max(1);
+            let mut table = Table::new();
+            table.add_row(Row::new(vec![
+                Cell::new("Range"),
+                Cell::new("Count"),
+                Cell::new(""),
+            ]));
+
+            for (lo, hi, count) in &profile.histogram {
+                let bar_len = (*count as f64 / max_count as f64 * 20.0).round() as usize;
+                table.add_row(Row::new(vec![
+                    Cell::new(&format!("[{}, {})", Self::format_bytes(*lo), Self::format_bytes(*hi))),
+                    Cell::new(&count.to_string()),
+                    Cell::new(&"#".repeat(bar_len)),
+                ]));
+            }
+
+            table.printstd();
+
+            if profile.overflow_count > 0 {
+                println!("... and {} samples above the largest bucket", profile.overflow_count);
+            }
+        }
+
+        println!();
+    }
+
+    fn print_cgroup_stats(&self, report: &ProfileReport) {
+        let Some(cgroup) = &report.cgroup_stats else {
+            return;
+        };
+
+        println!("=== CONTROL GROUP ===");
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Metric"),
+            Cell::new("Value"),
+        ]));
+
+        table.add_row(Row::new(vec![
+            Cell::new("Current"),
+            Cell::new(&Self::format_bytes(cgroup.current_bytes)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Peak"),
+            Cell::new(&Self::format_bytes(cgroup.peak_bytes)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Limit"),
+            Cell::new(&cgroup.limit_bytes.map_or_else(|| "unlimited".to_string(), Self::format_bytes)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Anon"),
+            Cell::new(&Self::format_bytes(cgroup.anon_bytes)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("File"),
+            Cell::new(&Self::format_bytes(cgroup.file_bytes)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Kernel"),
+            Cell::new(&Self::format_bytes(cgroup.kernel_bytes)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("CPU Usage"),
+            Cell::new(&format!("{:.2}s", cgroup.cpu_usage_usec as f64 / 1_000_000.0)),
+        ]));
+
+        table.printstd();
+        println!();
+    }
+
+    /// Render an ASCII sparkline of `current_usage` across `report.samples`, so
+    /// growth/plateau/leak-ramp behavior is visible rather than just the endpoint snapshot.
+    fn print_timeline(&self, report: &ProfileReport) {
+        if report.samples.is_empty() {
+            return;
+        }
+
+        const LEVELS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = report
+            .samples
+            .iter()
+            .map(|s| s.current_usage)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let sparkline: String = report
+            .samples
+            .iter()
+            .map(|s| {
+                let level = (s.current_usage * (LEVELS.len() - 1)) / max;
+                LEVELS[level]
+            })
+            .collect();
+
+        println!("=== MEMORY TIMELINE ===");
+        println!("{sparkline}  (0 .. {})", Self::format_bytes(max));
+
+        let avg_cpu = report.samples.iter().map(|s| s.cpu_percent).sum::<f64>()
+            / report.samples.len() as f64;
+        let peak_cpu = report
+            .samples
+            .iter()
+            .map(|s| s.cpu_percent)
+            .fold(0.0, f64::max);
+        println!("CPU Usage: avg {:.1}%, peak {:.1}%", avg_cpu, peak_cpu);
+        println!();
+    }
+
+    /// Emit `timestamp,current_usage,peak_usage,allocation_count` rows for `report.samples`.
+    pub fn generate_csv(&self, report: &ProfileReport) -> String {
+        let mut output = String::new();
+        writeln!(output, "timestamp,current_usage,peak_usage,allocation_count").unwrap();
+        for sample in &report.samples {
+            writeln!(
+                output,
+                "{},{},{},{}",
+                sample.timestamp, sample.current_usage, sample.peak_usage, sample.allocation_count
+            )
+            .unwrap();
+        }
+        output
+    }
+
+    fn print_stack_tree(&self, report: &ProfileReport) {
+        if report.memory_stats.active_allocations.is_empty() {
+            return;
+        }
+
+        println!("=== STACK TRACE TREE ===");
+        let tree = build_stack_tree(report, StackTreeFilter::parse("depth@8,longer_than=0"));
+        print!("{}", render_stack_tree(&tree));
+        println!();
     }
 
     fn print_memory_statistics(&self, report: &ProfileReport) {
@@ -96,24 +434,52 @@ impl ReportGenerator {
             println!("Largest leak: {}", Self::format_bytes(largest));
         }
 
-        println!("\nLeaks by size:");
-        let mut table = Table::new();
-        table.add_row(Row::new(vec![
-            Cell::new("Size"),
-            Cell::new("Count"),
-            Cell::new("Total"),
-        ]));
+        if !leak_summary.leaks_by_bucket.is_empty() {
+            println!("\nLeaks by size (log2 buckets):");
+            let total_bytes = leak_summary.total_leaked_bytes.max(1);
+            let mut table = Table::new();
+            table.add_row(Row::new(vec![
+                Cell::new("Range"),
+                Cell::new("Count"),
+                Cell::new("Total"),
+                Cell::new("% of Leaked"),
+                Cell::new(""),
+            ]));
 
-        for (size, count) in &leak_summary.leaks_by_size {
+            for (lo, hi, count, bytes) in &leak_summary.leaks_by_bucket {
+                let pct = *bytes as f64 / total_bytes as f64 * 100.0;
+                let bar_len = (pct / 100.0 * 20.0).round() as usize;
+                table.add_row(Row::new(vec![
+                    Cell::new(&format!("[{}, {})", Self::format_bytes(*lo as usize), Self::format_bytes(*hi as usize))),
+                    Cell::new(&count.to_string()),
+                    Cell::new(&Self::format_bytes(*bytes)),
+                    Cell::new(&format!("{:.1}%", pct)),
+                    Cell::new(&"#".repeat(bar_len)),
+                ]));
+            }
+
+            table.printstd();
+            println!();
+        } else {
+            println!("\nLeaks by size (exact):");
+            let mut table = Table::new();
             table.add_row(Row::new(vec![
-                Cell::new(&Self::format_bytes(*size)),
-                Cell::new(&count.to_string()),
-                Cell::new(&Self::format_bytes(size * count)),
+                Cell::new("Size"),
+                Cell::new("Count"),
+                Cell::new("Total"),
             ]));
-        }
 
-        table.printstd();
-        println!();
+            for (size, count) in &leak_summary.leaks_by_size {
+                table.add_row(Row::new(vec![
+                    Cell::new(&Self::format_bytes(*size)),
+                    Cell::new(&count.to_string()),
+                    Cell::new(&Self::format_bytes(size * count)),
+                ]));
+            }
+
+            table.printstd();
+            println!();
+        }
     }
 
     fn print_allocation_details(&self, report: &ProfileReport) {
@@ -198,17 +564,37 @@ impl ReportGenerator {
             }
             
             writeln!(output, "").unwrap();
-            writeln!(output, "### Leaks by Size").unwrap();
-            writeln!(output, "").unwrap();
-            writeln!(output, "| Size | Count | Total |").unwrap();
-            writeln!(output, "|------|-------|-------|").unwrap();
-            
-            for (size, count) in &leak_summary.leaks_by_size {
-                writeln!(output, "| {} | {} | {} |", 
-                    Self::format_bytes(*size), 
-                    count, 
-                    Self::format_bytes(size * count)
-                ).unwrap();
+
+            if !leak_summary.leaks_by_bucket.is_empty() {
+                writeln!(output, "### Leaks by Size (log2 buckets)").unwrap();
+                writeln!(output, "").unwrap();
+                writeln!(output, "| Range | Count | Total |").unwrap();
+                writeln!(output, "|-------|-------|-------|").unwrap();
+
+                for (lo, hi, count, bytes) in &leak_summary.leaks_by_bucket {
+                    writeln!(
+                        output,
+                        "| [{}, {}) | {} | {} |",
+                        Self::format_bytes(*lo as usize),
+                        Self::format_bytes(*hi as usize),
+                        count,
+                        Self::format_bytes(*bytes)
+                    )
+                    .unwrap();
+                }
+            } else {
+                writeln!(output, "### Leaks by Size").unwrap();
+                writeln!(output, "").unwrap();
+                writeln!(output, "| Size | Count | Total |").unwrap();
+                writeln!(output, "|------|-------|-------|").unwrap();
+
+                for (size, count) in &leak_summary.leaks_by_size {
+                    writeln!(output, "| {} | {} | {} |",
+                        Self::format_bytes(*size),
+                        count,
+                        Self::format_bytes(size * count)
+                    ).unwrap();
+                }
             }
         }
 